@@ -1,37 +1,14 @@
 #!/bin/bash
 
-POSITIONAL=()
+# Usage: ./run.sh [<profile>] [<search options>...]
+#
+# Profiles live in vanitygen.toml; options given here override the profile's.
 CMD_ARGS=""
-
-while [[ $# -gt 0 ]]
-do
-key="$1"
-case $key in
-    --threads)
-    THREADS="$2"
-    CMD_ARGS+="--threads ${THREADS} "
-    shift
-    shift
-    ;;
-    --jobs)
-    JOBS="$2"
-    CMD_ARGS+="--jobs ${JOBS} "
-    shift
-    shift
-    ;;
-    --each)
-    EACH="$2"
-    CMD_ARGS+="--each ${EACH} "
-    shift
-    shift
-    ;;
-    *)
-    POSITIONAL+=("$1")
-    CMD_ARGS+="$1 "
-    shift
-    ;;
-esac
-done
+if [[ $# -gt 0 && "$1" != -* ]]; then
+  CMD_ARGS+="--profile $1 "
+  shift
+fi
+CMD_ARGS+="$*"
 
 export CMD_ARGS
 
@@ -0,0 +1,146 @@
+use crate::criteria::{CriteriaPredicate, Score};
+
+/// The 40 lowercase hex digits of an address.
+fn digits(address: &str) -> Vec<u8> {
+    address
+        .trim_start_matches("0x")
+        .bytes()
+        .map(|b| b.to_ascii_lowercase())
+        .collect()
+}
+
+/// Length of the longest run of one repeated digit.
+fn longest_repeat(hex: &[u8]) -> usize {
+    hex.chunk_by(|a, b| a == b)
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Length of the longest run of digits that all belong to `class`.
+fn longest_class_run(hex: &[u8], class: impl Fn(&u8) -> bool) -> usize {
+    hex.split(|d| !class(d))
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Prefer the longest run of one repeated digit anywhere, as in `0x12aaaaaa34..`.
+#[derive(Copy, Clone)]
+pub struct RepeatedRunCriteria;
+
+impl CriteriaPredicate for RepeatedRunCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score::new([longest_repeat(&digits(address)) as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer addresses that start and end with a run of the same digit, as in
+/// `0x7777..7777`; the score is both runs together, or the longer one when
+/// the digits differ.
+#[derive(Copy, Clone)]
+pub struct RepeatedEndsCriteria;
+
+impl CriteriaPredicate for RepeatedEndsCriteria {
+    fn score(&self, address: &str) -> Score {
+        let hex = digits(address);
+        let mut runs = hex.chunk_by(|a, b| a == b);
+        let (Some(first), last) = (runs.next(), runs.next_back()) else {
+            return Score::new([0]);
+        };
+        let score = match last {
+            Some(last) if first[0] == last[0] => first.len() + last.len(),
+            Some(last) => first.len().max(last.len()),
+            None => first.len(),
+        };
+        Score::new([score as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer the longest palindromic stretch of digits, as in `0x..12344321..`.
+#[derive(Copy, Clone)]
+pub struct PalindromeCriteria;
+
+impl CriteriaPredicate for PalindromeCriteria {
+    fn score(&self, address: &str) -> Score {
+        let hex = digits(address);
+        let mut best = hex.len().min(1);
+        // expand around every odd (i, i) and even (i, i + 1) center
+        for center in 0..hex.len() * 2 {
+            let (mut left, mut right) = (center / 2, center / 2 + center % 2);
+            while right < hex.len() && hex[left] == hex[right] {
+                best = best.max(right - left + 1);
+                if left == 0 {
+                    break;
+                }
+                left -= 1;
+                right += 1;
+            }
+        }
+        Score::new([best as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer the longest stretch of decimal digits only, as in `0x..31415926..`;
+/// a score of 40 is an address without letters.
+#[derive(Copy, Clone)]
+pub struct DigitRunCriteria;
+
+impl CriteriaPredicate for DigitRunCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score::new([longest_class_run(&digits(address), u8::is_ascii_digit) as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer the longest stretch of the letters `a`-`f` only, as in `0x..facade..`;
+/// a score of 40 is an address without decimal digits.
+#[derive(Copy, Clone)]
+pub struct LetterRunCriteria;
+
+impl CriteriaPredicate for LetterRunCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score::new([longest_class_run(&digits(address), u8::is_ascii_alphabetic) as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer addresses whose end mirrors their start, as in `0xabc1..1cba`; the
+/// score is the number of digits mirrored at each end.
+#[derive(Copy, Clone)]
+pub struct MirrorCriteria;
+
+impl CriteriaPredicate for MirrorCriteria {
+    fn score(&self, address: &str) -> Score {
+        let hex = digits(address);
+        let mirrored = hex
+            .iter()
+            .zip(hex.iter().rev())
+            .take(hex.len() / 2)
+            .take_while(|(a, b)| a == b)
+            .count();
+        Score::new([mirrored as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use bip32::Language;
+use num_format::{Locale, ToFormattedString};
+use zeroize::Zeroizing;
+
+use crate::{
+    criteria::{CriteriaPredicate, LessThanCriteria},
+    crypto::{AddressGenerator, MnemonicAddressGenerator},
+    randnum::{Entropy, NumberGenerator, SeededNumberGenerator},
+    save::{SaveRule, Saver},
+    search::Searcher,
+};
+
+/// Attempts used when key derivation is skipped, so that dispatch overhead dominates.
+const OVERHEAD_ATTEMPTS: usize = 2_000_000;
+
+/// Stand-in address generator that skips key derivation entirely, so that the
+/// cost of the surrounding pipeline (and its dispatch) is what gets measured.
+#[derive(Copy, Clone)]
+struct EntropyAddressGenerator;
+
+impl AddressGenerator for EntropyAddressGenerator {
+    fn generate(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        Ok(format!("0x{}", hex::encode(&entropy[12..])))
+    }
+
+    fn secret(&self, entropy: &Entropy) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(entropy))
+    }
+
+    fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        self.generate(entropy)
+    }
+
+    fn kind(&self) -> &'static str {
+        "entropy"
+    }
+
+    fn derivation(&self, entropy: &Entropy) -> (&'static str, Zeroizing<String>) {
+        ("entropy", Zeroizing::new(hex::encode(entropy)))
+    }
+
+    fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Compare the monomorphized `Searcher` against the same pipeline built from
+/// boxed trait objects, with and without BIP39/BIP32 key derivation.
+pub fn run(mnemonic_attempts: usize) {
+    let mnemonic = MnemonicAddressGenerator {
+        language: Language::English,
+    };
+
+    println!(
+        "pipeline overhead ({} attempts, no key derivation)",
+        OVERHEAD_ATTEMPTS
+    );
+    compare(EntropyAddressGenerator, OVERHEAD_ATTEMPTS);
+
+    println!(
+        "full pipeline ({} attempts, mnemonic derivation)",
+        mnemonic_attempts
+    );
+    compare(mnemonic, mnemonic_attempts);
+}
+
+fn compare<A>(address_generator: A, attempts: usize)
+where
+    A: AddressGenerator + Clone + Send + Sync + 'static,
+{
+    let generic = time(Searcher::new(
+        SeededNumberGenerator::new(0),
+        address_generator.clone(),
+        LessThanCriteria,
+        attempts,
+    ));
+    let boxed = time(Searcher::new(
+        Box::new(SeededNumberGenerator::new(0)) as Box<dyn NumberGenerator + Send + Sync>,
+        Box::new(address_generator) as Box<dyn AddressGenerator + Send + Sync>,
+        Box::new(LessThanCriteria) as Box<dyn CriteriaPredicate + Send + Sync>,
+        attempts,
+    ));
+
+    report("generic", generic, attempts);
+    report("boxed", boxed, attempts);
+    println!(
+        "  boxed / generic: {:.3}x",
+        boxed.as_secs_f64() / generic.as_secs_f64()
+    );
+}
+
+fn time<N, A, C>(mut searcher: Searcher<N, A, C>) -> Duration
+where
+    N: NumberGenerator,
+    A: AddressGenerator,
+    C: CriteriaPredicate,
+{
+    let never = Saver {
+        rule: SaveRule::Never,
+        words: None,
+        sinks: Vec::new(),
+    };
+    let start = Instant::now();
+    let found = searcher.run(&never);
+    let elapsed = start.elapsed();
+    std::hint::black_box(found.best.address);
+    elapsed
+}
+
+fn report(name: &str, elapsed: Duration, attempts: usize) {
+    let per_sec = (attempts as f64 / elapsed.as_secs_f64()) as u64;
+    println!(
+        "  {:<8} {:>10.3?}  {:>14} attempts/sec",
+        name,
+        elapsed,
+        per_sec.to_formatted_string(&Locale::en)
+    );
+}
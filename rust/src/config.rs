@@ -0,0 +1,235 @@
+use std::{collections::BTreeSet, ffi::OsString, fmt::Write, fs, path::Path};
+
+use clap::{parser::ValueSource, ArgAction, ArgMatches, Command};
+use toml::{Table, Value};
+
+/// Config file read by `search --profile` when `--config` isn't given.
+pub const CONFIG_FILE: &str = "vanitygen.toml";
+
+/// Options that only make sense with the profile itself, never inside one.
+const NOT_PROFILE_OPTIONS: [&str; 4] = ["help", "profile", "config", "print-config"];
+
+/// Options whose values regenerate secrets. They're left out of effective
+/// configs, which end up in logs and the results database.
+const SECRET_OPTIONS: [&str; 1] = ["seed"];
+
+/// Options that pick the same thing in different ways: setting any of them on
+/// the command line replaces all of them from the profile, so that e.g.
+/// `--pattern` overrides a profile's `prefix` instead of conflicting with it.
+const ALTERNATIVES: [&[&str]; 3] = [
+    &["criteria", "prefix", "pattern", "rank", "words"],
+    &["seed", "shard", "counter-base", "extra-entropy"],
+    &["create2-deployer", "init-code-hash", "hook-flags"],
+];
+
+/// Split `--no-<option>` arguments, which drop `<option>` from the profile, off
+/// a `search` command line, returning the rest and the dropped options.
+///
+/// Only profile options are taken, so anything else is still left for clap to
+/// reject.
+pub fn take_unset_options(search: &Command, argv: Vec<OsString>) -> (Vec<OsString>, Vec<String>) {
+    if argv.get(1).is_none_or(|command| command != "search") {
+        return (argv, Vec::new());
+    }
+    let mut unset = Vec::new();
+    let argv = argv
+        .into_iter()
+        .filter(|arg| {
+            let key = arg.to_str().and_then(|arg| arg.strip_prefix("--no-"));
+            match key {
+                Some(key)
+                    if !NOT_PROFILE_OPTIONS.contains(&key)
+                        && search.get_arguments().any(|a| a.get_long() == Some(key)) =>
+                {
+                    unset.push(key.to_string());
+                    false
+                }
+                _ => true,
+            }
+        })
+        .collect();
+    (argv, unset)
+}
+
+/// A named set of `search` options from a `[profile.<name>]` table, keyed by
+/// their long flag names, e.g.
+///
+/// ```toml
+/// [profile.zero8]
+/// threads = 32
+/// criteria = "leading-zero-bytes"
+/// save = "zeros:8"
+/// save-sink = ["mnemonics", "file:/var/log/vanitygen/zero8.log"]
+/// ```
+pub struct Profile {
+    pub name: String,
+    options: Table,
+}
+
+impl Profile {
+    /// Load profile `name` from the TOML file at `path`.
+    pub fn load(path: &Path, name: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config {}: {}", path.display(), e))?;
+        let mut config: Table = contents
+            .parse()
+            .map_err(|e| format!("failed to parse config {}: {}", path.display(), e))?;
+        let mut profiles = match config.remove("profile") {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err(format!("'profile' in {} is not a table", path.display())),
+            None => Table::new(),
+        };
+        match profiles.remove(name) {
+            Some(Value::Table(options)) => Ok(Self {
+                name: name.to_string(),
+                options,
+            }),
+            Some(_) => Err(format!("profile '{}' is not a table", name)),
+            None => Err(format!(
+                "no profile '{}' in {}; it has {}",
+                name,
+                path.display(),
+                if profiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                }
+            )),
+        }
+    }
+
+    /// The profile's options as arguments to the `search` subcommand, leaving
+    /// out the ones `matches` already has from the command line and the ones
+    /// `unset` with `--no-<option>`. Returns the arguments and the options they set.
+    pub fn args(
+        &self,
+        search: &Command,
+        matches: &ArgMatches,
+        unset: &[String],
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let on_command_line: BTreeSet<&str> = search
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .filter_map(|arg| arg.get_long())
+            .chain(unset.iter().map(String::as_str))
+            .collect();
+        let overridden = |key: &str| {
+            on_command_line.contains(key)
+                || ALTERNATIVES.iter().any(|alternatives| {
+                    alternatives.contains(&key)
+                        && alternatives.iter().any(|a| on_command_line.contains(a))
+                })
+        };
+
+        let mut args = Vec::new();
+        let mut keys = Vec::new();
+        for (key, value) in &self.options {
+            let arg = search
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(key.as_str()))
+                .filter(|_| !NOT_PROFILE_OPTIONS.contains(&key.as_str()))
+                .ok_or_else(|| format!("unknown option '{}' in profile '{}'", key, self.name))?;
+            if overridden(key) {
+                continue;
+            }
+            let flag = matches!(arg.get_action(), ArgAction::SetTrue);
+            push_args(&mut args, key, value, flag)
+                .map_err(|e| format!("{} in profile '{}'", e, self.name))?;
+            keys.push(key.clone());
+        }
+        Ok((args, keys))
+    }
+}
+
+fn push_args(args: &mut Vec<String>, key: &str, value: &Value, flag: bool) -> Result<(), String> {
+    match value {
+        Value::Boolean(set) if flag => {
+            if *set {
+                args.push(format!("--{}", key));
+            }
+        }
+        Value::String(s) if !flag => args.push(format!("--{}={}", key, s)),
+        Value::Integer(n) if !flag => args.push(format!("--{}={}", key, n)),
+        Value::Float(x) if !flag => args.push(format!("--{}={}", key, x)),
+        Value::Array(values) if !flag => {
+            for value in values {
+                push_args(args, key, value, flag)?;
+            }
+        }
+        _ => return Err(format!("option '{}' can't be {}", key, value.type_str())),
+    }
+    Ok(())
+}
+
+/// The options set for a search, written as a profile that can be pasted into
+/// the config file, noting where each value came from. Secret options are
+/// commented out with their values redacted.
+pub fn effective_config(
+    search: &Command,
+    matches: &ArgMatches,
+    profile: Option<(&str, &[String])>,
+) -> String {
+    let (name, from_profile) = profile.unwrap_or(("default", &[]));
+    let mut lines = Vec::new();
+    for arg in search.get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(key) = arg
+            .get_long()
+            .filter(|key| !NOT_PROFILE_OPTIONS.contains(key))
+        else {
+            continue;
+        };
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let value = if matches!(arg.get_action(), ArgAction::SetTrue) {
+            Value::Boolean(matches.get_flag(id))
+        } else {
+            let mut values: Vec<Value> = matches
+                .get_raw(id)
+                .into_iter()
+                .flatten()
+                .map(|raw| {
+                    // numbers stay numbers, unless that would lose digits as in `prefix = "00"`
+                    let raw = raw.to_string_lossy();
+                    match raw.parse::<i64>() {
+                        Ok(n) if n.to_string() == raw => Value::Integer(n),
+                        _ => Value::String(raw.into_owned()),
+                    }
+                })
+                .collect();
+            if matches!(arg.get_action(), ArgAction::Append) {
+                Value::Array(values)
+            } else {
+                values.remove(0)
+            }
+        };
+        let source = if from_profile.iter().any(|k| k == key) {
+            format!("profile {}", name)
+        } else {
+            "command line".to_string()
+        };
+        let line = if SECRET_OPTIONS.contains(&key) {
+            format!("# {} = <redacted>", key)
+        } else {
+            format!("{} = {}", key, value)
+        };
+        lines.push((line, source));
+    }
+
+    // align the sources, without letting one long hash push them all out
+    let width = lines
+        .iter()
+        .map(|(line, _)| line.len())
+        .filter(|len| *len <= 40)
+        .max()
+        .unwrap_or(0);
+    let mut config = format!("[profile.{}]\n", name);
+    for (line, source) in lines {
+        let _ = writeln!(config, "{:width$}  # {}", line, source, width = width);
+    }
+    config.push_str("# options not listed use their defaults, see `search --help`\n");
+    config
+}
@@ -1,19 +1,908 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    aesthetic::{
+        DigitRunCriteria, LetterRunCriteria, MirrorCriteria, PalindromeCriteria,
+        RepeatedEndsCriteria, RepeatedRunCriteria,
+    },
+    crypto::to_checksum_address,
+    dictionary::{Dictionary, DictionaryCriteria, WordPosition},
+    pattern::PatternCriteria,
+};
+
+/// How good an address is under some criteria, where higher is better.
+///
+/// Scores compare part by part, so the first part is the headline figure and
+/// each later part only breaks ties left by the ones before it. A criteria
+/// gives every address the same number of parts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Score(pub Vec<u64>);
+
+impl Score {
+    pub fn new(parts: impl Into<Vec<u64>>) -> Self {
+        Self(parts.into())
+    }
+
+    /// The headline part, or 0 for an empty score.
+    pub fn headline(&self) -> u64 {
+        self.0.first().copied().unwrap_or_default()
+    }
+
+    /// Pack the first two parts into a single number that never contradicts
+    /// the full comparison: `a > b` implies `a.coarse() >= b.coarse()`.
+    pub fn coarse(&self) -> u64 {
+        const TOP: u64 = u32::MAX as u64;
+        let first = self.headline();
+        if first >= TOP {
+            return TOP << 32;
+        }
+        first << 32 | self.0.get(1).map_or(0, |second| second >> 32)
+    }
+
+    /// Whether this score reaches `done`, the score of a full match.
+    pub fn reaches(&self, done: &Score) -> bool {
+        self.cmp(done) != Ordering::Less
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.headline())
+    }
+}
+
 pub trait CriteriaPredicate {
-    /// Test whether the given address is better than the current best address.
-    fn better(&self, address: &str, other: &str) -> bool;
+    /// Score the given address; the searcher keeps the highest scores.
+    fn score(&self, address: &str) -> Score;
 
-    fn clone_box(&self) -> Box<dyn CriteriaPredicate>;
+    /// The lowest score that fully satisfies the criteria, for criteria that
+    /// have such a goal. The search stops once an address reaches it.
+    fn done(&self) -> Option<Score> {
+        None
+    }
+
+    /// Test whether the given address is better than the other address.
+    fn better(&self, address: &str, other: &str) -> bool {
+        self.score(address) > self.score(other)
+    }
+
+    /// Expected number of attempts to find an address that fully satisfies the
+    /// criteria, for criteria that have such a goal.
+    fn difficulty(&self) -> Option<f64> {
+        None
+    }
+
+    /// Expected number of attempts for the headline part of the score to reach
+    /// `min`, as with `>=<min>` in a criteria expression, where it is known.
+    fn difficulty_of(&self, _min: u64) -> Option<f64> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync>;
 }
 
+impl CriteriaPredicate for Box<dyn CriteriaPredicate + Send + Sync> {
+    fn score(&self, address: &str) -> Score {
+        (**self).score(address)
+    }
+
+    fn done(&self) -> Option<Score> {
+        (**self).done()
+    }
+
+    fn better(&self, address: &str, other: &str) -> bool {
+        (**self).better(address, other)
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        (**self).difficulty()
+    }
+
+    fn difficulty_of(&self, min: u64) -> Option<f64> {
+        (**self).difficulty_of(min)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        (**self).clone_box()
+    }
+}
+
+impl Clone for Box<dyn CriteriaPredicate + Send + Sync> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Prefer the lowest address in hex order.
 #[derive(Copy, Clone)]
 pub struct LessThanCriteria;
 
 impl CriteriaPredicate for LessThanCriteria {
-    fn better(&self, address: &str, other: &str) -> bool {
-        address < other
+    /// The number of leading zero digits, then the complement of the address
+    /// in 64-bit words, so that smaller addresses score higher.
+    fn score(&self, address: &str) -> Score {
+        let hex = address.trim_start_matches("0x");
+        let word = |range: Range<usize>| {
+            hex.get(range)
+                .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+        };
+        match (word(0..16), word(16..32), word(32..40)) {
+            (Some(high), Some(middle), Some(low)) => {
+                let zeros = hex.chars().take_while(|c| *c == '0').count() as u64;
+                Score::new([zeros, !high, !middle, !low])
+            }
+            _ => Score::new([0; 4]),
+        }
+    }
+
+    fn difficulty_of(&self, min: u64) -> Option<f64> {
+        (min <= 40).then(|| 16f64.powi(min as i32))
     }
 
-    fn clone_box(&self) -> Box<dyn CriteriaPredicate> {
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
         Box::new(*self)
     }
 }
+
+/// Calldata gas saved per zero byte: 4 gas instead of 16 (EIP-2028).
+pub const ZERO_BYTE_GAS_SAVING: u64 = 16 - 4;
+
+/// Count of leading zero bytes and of zero bytes anywhere in a `0x`-prefixed address.
+pub fn zero_bytes(address: &str) -> (u64, u64) {
+    let mut bytes = [0u8; 20];
+    if hex::decode_to_slice(address.trim_start_matches("0x"), &mut bytes).is_err() {
+        return (0, 0);
+    }
+    let leading = bytes.iter().take_while(|b| **b == 0).count() as u64;
+    let total = bytes.iter().filter(|b| **b == 0).count() as u64;
+    (leading, total)
+}
+
+/// Estimated calldata gas saved each time the address is passed in a transaction.
+pub fn calldata_gas_savings(address: &str) -> u64 {
+    zero_bytes(address).1 * ZERO_BYTE_GAS_SAVING
+}
+
+/// Prefer more leading zero bytes, breaking ties by the total number of zero bytes.
+#[derive(Copy, Clone)]
+pub struct LeadingZeroBytesCriteria;
+
+impl CriteriaPredicate for LeadingZeroBytesCriteria {
+    fn score(&self, address: &str) -> Score {
+        let (leading, total) = zero_bytes(address);
+        Score::new([leading, total])
+    }
+
+    fn difficulty_of(&self, min: u64) -> Option<f64> {
+        (min <= 20).then(|| 256f64.powi(min as i32))
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Prefer more zero bytes anywhere in the address, which maximizes calldata gas
+/// savings; ties go to more leading zero bytes.
+#[derive(Copy, Clone)]
+pub struct ZeroBytesCriteria;
+
+impl CriteriaPredicate for ZeroBytesCriteria {
+    fn score(&self, address: &str) -> Score {
+        let (leading, total) = zero_bytes(address);
+        Score::new([total, leading])
+    }
+
+    /// Each of the 20 bytes is zero with odds 1/256, independently.
+    fn difficulty_of(&self, min: u64) -> Option<f64> {
+        let p: f64 = 1.0 / 256.0;
+        let mut ways = 1.0;
+        let mut odds = 0.0;
+        for k in 0..=20 {
+            if k >= min {
+                odds += ways * p.powi(k as i32) * (1.0 - p).powi(20 - k as i32);
+            }
+            ways = ways * (20 - k) as f64 / (k + 1) as f64;
+        }
+        (odds > 0.0).then(|| 1.0 / odds)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Uniswap v4 hook permissions, encoded in the lowest 14 bits of the hook's address.
+pub const HOOK_PERMISSIONS: [(&str, u16); 14] = [
+    ("before-initialize", 1 << 13),
+    ("after-initialize", 1 << 12),
+    ("before-add-liquidity", 1 << 11),
+    ("after-add-liquidity", 1 << 10),
+    ("before-remove-liquidity", 1 << 9),
+    ("after-remove-liquidity", 1 << 8),
+    ("before-swap", 1 << 7),
+    ("after-swap", 1 << 6),
+    ("before-donate", 1 << 5),
+    ("after-donate", 1 << 4),
+    ("before-swap-returns-delta", 1 << 3),
+    ("after-swap-returns-delta", 1 << 2),
+    ("after-add-liquidity-returns-delta", 1 << 1),
+    ("after-remove-liquidity-returns-delta", 1 << 0),
+];
+
+/// Every bit a Uniswap v4 hook address reserves for permissions.
+pub const ALL_HOOK_MASK: u16 = (1 << 14) - 1;
+
+/// A set of hook permissions, written as comma-separated names such as
+/// `before-swap,after-swap`, or `none` for a hook with no permissions.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HookFlags(pub u16);
+
+impl FromStr for HookFlags {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(Self(0));
+        }
+        s.split(',')
+            .map(|name| {
+                HOOK_PERMISSIONS
+                    .iter()
+                    .find(|(n, _)| *n == name.trim())
+                    .map(|(_, bit)| *bit)
+                    .ok_or_else(|| {
+                        let known: Vec<&str> = HOOK_PERMISSIONS.iter().map(|(n, _)| *n).collect();
+                        format!(
+                            "unknown hook permission '{}', expected one of {}",
+                            name,
+                            known.join(", ")
+                        )
+                    })
+            })
+            .try_fold(0, |flags, bit| bit.map(|bit| flags | bit))
+            .map(Self)
+    }
+}
+
+impl fmt::Display for HookFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = HOOK_PERMISSIONS
+            .iter()
+            .filter(|(_, bit)| self.0 & bit != 0)
+            .map(|(n, _)| *n)
+            .collect();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{} (0x{:04x})", names.join(","), self.0)
+        }
+    }
+}
+
+/// Requires the hook permission bits of the address to equal `flags` exactly,
+/// ranking addresses that agree on that by `then`.
+#[derive(Copy, Clone)]
+pub struct HookFlagsCriteria<C> {
+    pub flags: HookFlags,
+    pub then: C,
+}
+
+impl<C> HookFlagsCriteria<C> {
+    pub fn matches(&self, address: &str) -> bool {
+        address
+            .len()
+            .checked_sub(4)
+            .and_then(|start| u16::from_str_radix(&address[start..], 16).ok())
+            .is_some_and(|low| low & ALL_HOOK_MASK == self.flags.0)
+    }
+}
+
+impl<C> CriteriaPredicate for HookFlagsCriteria<C>
+where
+    C: CriteriaPredicate + Clone + Send + Sync + 'static,
+{
+    fn score(&self, address: &str) -> Score {
+        let mut score = vec![self.matches(address) as u64];
+        score.extend(self.then.score(address).0);
+        Score(score)
+    }
+
+    /// Only once `then` reaches its own goal: with no goal to reach, a hook flag
+    /// match alone must not end the search.
+    fn done(&self) -> Option<Score> {
+        let mut done = vec![1];
+        done.extend(self.then.done()?.0);
+        Some(Score(done))
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        let hook_bits = 2f64.powi(ALL_HOOK_MASK.count_ones() as i32);
+        Some(self.then.difficulty().unwrap_or(1.0) * hook_bits)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Prefer addresses that start with more of `pattern`.
+///
+/// In case-sensitive mode the letters of the pattern must also match the
+/// address's EIP-55 checksum casing, which halves the odds per letter.
+#[derive(Clone)]
+pub struct PrefixCriteria {
+    pattern: String,
+    case_sensitive: bool,
+}
+
+impl PrefixCriteria {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Self, String> {
+        let pattern = pattern.trim_start_matches("0x");
+        if pattern.is_empty()
+            || pattern.len() > 40
+            || !pattern.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(format!(
+                "'{}' is not a hex prefix of at most 40 digits",
+                pattern
+            ));
+        }
+        Ok(Self {
+            pattern: if case_sensitive {
+                pattern.to_string()
+            } else {
+                pattern.to_ascii_lowercase()
+            },
+            case_sensitive,
+        })
+    }
+
+    /// How many leading digits of the address match the pattern.
+    pub fn matched_len(&self, address: &str) -> usize {
+        let hex = address.trim_start_matches("0x");
+        let matched = hex
+            .chars()
+            .zip(self.pattern.chars())
+            .take_while(|(a, p)| a.eq_ignore_ascii_case(p))
+            .count();
+        if !self.case_sensitive
+            || !self.pattern[..matched]
+                .chars()
+                .any(|c| c.is_ascii_alphabetic())
+        {
+            return matched;
+        }
+        // only hash for the checksum once the digits already match
+        to_checksum_address(address)[2..]
+            .chars()
+            .zip(self.pattern.chars())
+            .take(matched)
+            .take_while(|(a, p)| a == p)
+            .count()
+    }
+}
+
+impl CriteriaPredicate for PrefixCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score::new([self.matched_len(address) as u64])
+    }
+
+    fn done(&self) -> Option<Score> {
+        Some(Score::new([self.pattern.len() as u64]))
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        self.difficulty_of(self.pattern.len() as u64)
+    }
+
+    /// Matching the first `min` digits of the pattern.
+    fn difficulty_of(&self, min: u64) -> Option<f64> {
+        let digits = self.pattern.get(..min as usize)?;
+        let letters = if self.case_sensitive {
+            digits.chars().filter(|c| c.is_ascii_alphabetic()).count()
+        } else {
+            0
+        };
+        Some(16f64.powi(digits.len() as i32) * 2f64.powi(letters as i32))
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+type BoxedCriteria = Box<dyn CriteriaPredicate + Send + Sync>;
+
+/// Whether `score` reaches `goal`; children without a goal never do.
+fn satisfied(score: &Score, goal: &Option<Score>) -> bool {
+    goal.as_ref().is_some_and(|goal| score.reaches(goal))
+}
+
+/// Difficulties of the children with a goal, or `None` if any of them is unknown.
+fn goal_difficulties(
+    children: &[BoxedCriteria],
+    goals: &[Option<Score>],
+) -> Option<impl Iterator<Item = f64>> {
+    let difficulties = children
+        .iter()
+        .zip(goals)
+        .filter(|(_, goal)| goal.is_some())
+        .map(|(child, _)| child.difficulty())
+        .collect::<Option<Vec<f64>>>()?;
+    Some(difficulties.into_iter())
+}
+
+/// Turns any criteria into one with a goal: done once the headline part of
+/// the score reaches `min`, as in `leading-zero-bytes>=2`.
+#[derive(Clone)]
+pub struct AtLeastCriteria {
+    pub inner: BoxedCriteria,
+    pub min: u64,
+}
+
+impl CriteriaPredicate for AtLeastCriteria {
+    fn score(&self, address: &str) -> Score {
+        self.inner.score(address)
+    }
+
+    fn done(&self) -> Option<Score> {
+        Some(Score::new([self.min]))
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        self.inner.difficulty_of(self.min)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Prefer addresses that meet more of the children's goals, then a higher sum
+/// of headlines; done once every child with a goal meets it.
+#[derive(Clone)]
+pub struct AllCriteria {
+    children: Vec<BoxedCriteria>,
+    goals: Vec<Option<Score>>,
+}
+
+impl AllCriteria {
+    pub fn new(children: Vec<BoxedCriteria>) -> Self {
+        let goals = children.iter().map(|c| c.done()).collect();
+        Self { children, goals }
+    }
+}
+
+impl CriteriaPredicate for AllCriteria {
+    fn score(&self, address: &str) -> Score {
+        let (met, sum) =
+            self.children
+                .iter()
+                .zip(&self.goals)
+                .fold((0, 0u64), |(met, sum), (child, goal)| {
+                    let score = child.score(address);
+                    (
+                        met + satisfied(&score, goal) as u64,
+                        sum.saturating_add(score.headline()),
+                    )
+                });
+        Score::new([met, sum])
+    }
+
+    fn done(&self) -> Option<Score> {
+        let goals = self.goals.iter().flatten().count() as u64;
+        (goals > 0).then(|| Score::new([goals]))
+    }
+
+    /// Assumes the children's goals are independent.
+    fn difficulty(&self) -> Option<f64> {
+        goal_difficulties(&self.children, &self.goals)?.reduce(|a, b| a * b)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Prefer addresses that meet any of the children's goals, then the highest
+/// headline of any child; done once one child with a goal meets it.
+#[derive(Clone)]
+pub struct AnyCriteria {
+    children: Vec<BoxedCriteria>,
+    goals: Vec<Option<Score>>,
+}
+
+impl AnyCriteria {
+    pub fn new(children: Vec<BoxedCriteria>) -> Self {
+        let goals = children.iter().map(|c| c.done()).collect();
+        Self { children, goals }
+    }
+}
+
+impl CriteriaPredicate for AnyCriteria {
+    fn score(&self, address: &str) -> Score {
+        let (met, max) =
+            self.children
+                .iter()
+                .zip(&self.goals)
+                .fold((false, 0), |(met, max), (child, goal)| {
+                    let score = child.score(address);
+                    (met || satisfied(&score, goal), max.max(score.headline()))
+                });
+        Score::new([met as u64, max])
+    }
+
+    fn done(&self) -> Option<Score> {
+        self.goals
+            .iter()
+            .any(Option::is_some)
+            .then(|| Score::new([1]))
+    }
+
+    /// Assumes the children's goals are independent.
+    fn difficulty(&self) -> Option<f64> {
+        let odds: f64 = goal_difficulties(&self.children, &self.goals)?
+            .map(|attempts| 1.0 / attempts)
+            .sum();
+        (odds > 0.0).then(|| 1.0 / odds)
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Prefer the highest weighted sum of the children's headlines. It has no
+/// goal of its own, since a high sum says little about any one child.
+#[derive(Clone)]
+pub struct WeightedCriteria {
+    pub children: Vec<(u64, BoxedCriteria)>,
+}
+
+impl CriteriaPredicate for WeightedCriteria {
+    fn score(&self, address: &str) -> Score {
+        let sum = self.children.iter().fold(0u64, |sum, (weight, child)| {
+            sum.saturating_add(weight.saturating_mul(child.score(address).headline()))
+        });
+        Score::new([sum])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Rank by the first child, breaking ties with each following child in turn.
+///
+/// The children's scores are concatenated, which relies on every criteria
+/// scoring all addresses with the same number of parts. It is done once the
+/// first child is, so the later children only order the results found so far.
+#[derive(Clone)]
+pub struct LexicographicCriteria {
+    pub children: Vec<BoxedCriteria>,
+}
+
+impl CriteriaPredicate for LexicographicCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score(
+            self.children
+                .iter()
+                .flat_map(|c| c.score(address).0)
+                .collect(),
+        )
+    }
+
+    fn done(&self) -> Option<Score> {
+        self.children.first().and_then(|c| c.done())
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        self.children.first().and_then(|c| c.difficulty())
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Parse a criteria expression such as
+/// `all(prefix:dead, leading-zero-bytes>=2)` or `lex(zero-bytes, less-than)`.
+///
+/// Leaves are `less-than`, `leading-zero-bytes`, `zero-bytes`, the aesthetic
+/// `repeated-run`, `repeated-ends`, `palindrome`, `digit-run`, `letter-run` and
+/// `mirror`, `prefix:<hex>`, `pattern:<pattern>` and the dictionary words
+/// `words:<wordlist>` (anywhere), `words-start:..` and `words-end:..`, optionally followed by `>=<n>` to give them a goal.
+/// They combine with `all(..)`, `any(..)`, `lex(..)` and `weighted(..)`, whose
+/// children are written `<weight>*<criteria>`. `case_sensitive` applies to
+/// every prefix and pattern in the expression.
+pub fn parse_criteria(spec: &str, case_sensitive: bool) -> Result<BoxedCriteria, String> {
+    parse_with_wordlists(spec, case_sensitive, Wordlists::Anywhere)
+}
+
+/// Parse criteria submitted from elsewhere, such as to `serve`, whose `words:`
+/// leaves may only name a file directly inside `wordlist_dir`, and aren't
+/// allowed at all without one.
+pub fn parse_submitted_criteria(
+    spec: &str,
+    case_sensitive: bool,
+    wordlist_dir: Option<&Path>,
+) -> Result<BoxedCriteria, String> {
+    parse_with_wordlists(spec, case_sensitive, Wordlists::Within(wordlist_dir))
+}
+
+/// Where `words:` leaves may load their wordlists from.
+#[derive(Copy, Clone)]
+enum Wordlists<'a> {
+    Anywhere,
+    Within(Option<&'a Path>),
+}
+
+fn parse_with_wordlists(
+    spec: &str,
+    case_sensitive: bool,
+    wordlists: Wordlists,
+) -> Result<BoxedCriteria, String> {
+    let mut parser = SpecParser {
+        rest: spec,
+        case_sensitive,
+        wordlists,
+    };
+    let criteria = parser.criteria()?;
+    if !parser.rest.trim().is_empty() {
+        return Err(format!("unexpected '{}' in criteria", parser.rest.trim()));
+    }
+    Ok(criteria)
+}
+
+struct SpecParser<'a> {
+    rest: &'a str,
+    case_sensitive: bool,
+    wordlists: Wordlists<'a>,
+}
+
+impl SpecParser<'_> {
+    /// Consume `token` if the input continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consume everything up to the next delimiter.
+    fn word(&mut self) -> &str {
+        self.rest = self.rest.trim_start();
+        let end = self
+            .rest
+            .find(|c: char| "(),*>".contains(c) || c.is_whitespace())
+            .unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        word
+    }
+
+    fn number(&mut self) -> Result<u64, String> {
+        let word = self.word();
+        word.parse()
+            .map_err(|_| format!("expected a number, got '{}'", word))
+    }
+
+    fn criteria(&mut self) -> Result<BoxedCriteria, String> {
+        let name = self.word().to_string();
+        let criteria: BoxedCriteria = if self.eat("(") {
+            match name.as_str() {
+                "all" => Box::new(AllCriteria::new(self.list(Self::criteria)?)),
+                "any" => Box::new(AnyCriteria::new(self.list(Self::criteria)?)),
+                "lex" => Box::new(LexicographicCriteria {
+                    children: self.list(Self::criteria)?,
+                }),
+                "weighted" => Box::new(WeightedCriteria {
+                    children: self.list(|parser| {
+                        let weight = parser.number()?;
+                        if !parser.eat("*") {
+                            return Err("expected '<weight>*<criteria>' in weighted(..)".into());
+                        }
+                        Ok((weight, parser.criteria()?))
+                    })?,
+                }),
+                _ => return Err(format!("unknown combinator '{}'", name)),
+            }
+        } else {
+            self.leaf(&name)?
+        };
+        if self.eat(">=") {
+            let min = self.number()?;
+            return Ok(Box::new(AtLeastCriteria {
+                inner: criteria,
+                min,
+            }));
+        }
+        Ok(criteria)
+    }
+
+    /// Comma-separated items up to the closing parenthesis.
+    fn list<T>(&mut self, item: impl Fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let mut items = vec![item(self)?];
+        while self.eat(",") {
+            items.push(item(self)?);
+        }
+        if !self.eat(")") {
+            return Err("expected ',' or ')' in criteria".into());
+        }
+        Ok(items)
+    }
+
+    fn leaf(&self, name: &str) -> Result<BoxedCriteria, String> {
+        let (kind, arg) = match name.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (name, None),
+        };
+        match (kind, arg) {
+            ("less-than", None) => Ok(Box::new(LessThanCriteria)),
+            ("leading-zero-bytes", None) => Ok(Box::new(LeadingZeroBytesCriteria)),
+            ("zero-bytes" | "gas", None) => Ok(Box::new(ZeroBytesCriteria)),
+            ("repeated-run", None) => Ok(Box::new(RepeatedRunCriteria)),
+            ("repeated-ends", None) => Ok(Box::new(RepeatedEndsCriteria)),
+            ("palindrome", None) => Ok(Box::new(PalindromeCriteria)),
+            ("digit-run", None) => Ok(Box::new(DigitRunCriteria)),
+            ("letter-run", None) => Ok(Box::new(LetterRunCriteria)),
+            ("mirror", None) => Ok(Box::new(MirrorCriteria)),
+            ("words" | "words-start" | "words-end", Some(path)) => {
+                let position = match kind {
+                    "words-start" => WordPosition::Start,
+                    "words-end" => WordPosition::End,
+                    _ => WordPosition::Anywhere,
+                };
+                let path = match self.wordlists {
+                    Wordlists::Anywhere => PathBuf::from(path),
+                    Wordlists::Within(None) => {
+                        return Err("words criteria aren't allowed without a wordlist directory".into())
+                    }
+                    Wordlists::Within(Some(dir)) => {
+                        if path.is_empty() || path.starts_with('.') || path.contains(['/', '\\']) {
+                            return Err(format!("'{}' is not a wordlist name", path));
+                        }
+                        dir.join(path)
+                    }
+                };
+                Ok(Box::new(DictionaryCriteria {
+                    dictionary: Arc::new(Dictionary::load(&path)?),
+                    position,
+                }))
+            }
+            ("prefix", Some(hex)) => Ok(Box::new(PrefixCriteria::new(hex, self.case_sensitive)?)),
+            ("pattern", Some(pattern)) => Ok(Box::new(PatternCriteria::new(
+                pattern,
+                self.case_sensitive,
+            )?)),
+            _ => Err(format!(
+                "unknown criteria '{}', expected less-than, leading-zero-bytes, zero-bytes, repeated-run, repeated-ends, palindrome, digit-run, letter-run, mirror, prefix:<hex>, pattern:<pattern> or words:<wordlist>",
+                name
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An address whose last two bytes carry `flags`, with `body` in front.
+    fn hook_address(body: &str, flags: HookFlags) -> String {
+        format!("0x{}{:04x}", body, flags.0)
+    }
+
+    #[test]
+    fn hook_match_alone_does_not_end_the_search() {
+        let flags: HookFlags = "before-swap,after-swap".parse().unwrap();
+        let criteria = HookFlagsCriteria {
+            flags,
+            then: ZeroBytesCriteria,
+        };
+        let address = hook_address(&"ab".repeat(18), flags);
+        assert!(criteria.matches(&address));
+        assert!(criteria.done().is_none());
+    }
+
+    #[test]
+    fn hook_match_ends_the_search_once_then_reaches_its_goal() {
+        let flags: HookFlags = "before-swap".parse().unwrap();
+        let criteria = HookFlagsCriteria {
+            flags,
+            then: PrefixCriteria::new("dead", false).unwrap(),
+        };
+        let done = criteria.done().unwrap();
+        let hook_only = hook_address(&"ab".repeat(18), flags);
+        let prefix_only = format!("0xdead{}0000", "ab".repeat(16));
+        let both = hook_address(&format!("dead{}", "ab".repeat(16)), flags);
+        assert!(!criteria.score(&hook_only).reaches(&done));
+        assert!(!criteria.score(&prefix_only).reaches(&done));
+        assert!(criteria.score(&both).reaches(&done));
+    }
+
+    fn parse_error(spec: &str) -> String {
+        match parse_criteria(spec, false) {
+            Ok(_) => panic!("'{}' parsed", spec),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn spec_combinators_score_their_children() {
+        let address = format!("0x0000dead{}", "ab".repeat(16));
+
+        let all = parse_criteria("all(prefix:0000, leading-zero-bytes>=3)", false).unwrap();
+        assert_eq!(all.done(), Some(Score::new([2])));
+        // the prefix goal is met, and the headlines sum to 4 digits + 2 bytes
+        assert_eq!(all.score(&address), Score::new([1, 6]));
+
+        let any = parse_criteria("any(prefix:0000, leading-zero-bytes>=3)", false).unwrap();
+        assert_eq!(any.done(), Some(Score::new([1])));
+        assert_eq!(any.score(&address), Score::new([1, 4]));
+
+        let weighted =
+            parse_criteria(" weighted( 3*leading-zero-bytes , 1*zero-bytes )", false).unwrap();
+        assert_eq!(weighted.done(), None);
+        assert_eq!(weighted.score(&address), Score::new([3 * 2 + 2]));
+
+        let lex = parse_criteria("lex(zero-bytes, less-than)", false).unwrap();
+        assert_eq!(lex.score(&address).0.len(), 2 + 4);
+        assert_eq!(lex.score(&address).0[..2], [2, 2]);
+    }
+
+    #[test]
+    fn spec_goals_give_a_difficulty() {
+        let at_least = parse_criteria("leading-zero-bytes>=2", false).unwrap();
+        assert_eq!(at_least.done(), Some(Score::new([2])));
+        assert_eq!(at_least.difficulty(), Some(65536.0));
+
+        let all = parse_criteria("all(prefix:dead, leading-zero-bytes>=2)", false).unwrap();
+        assert_eq!(all.difficulty(), Some(65536.0 * 65536.0));
+
+        let partial_prefix = parse_criteria("prefix:dead>=2", false).unwrap();
+        assert_eq!(partial_prefix.difficulty(), Some(256.0));
+
+        assert_eq!(
+            parse_criteria("palindrome>=8", false).unwrap().difficulty(),
+            None
+        );
+    }
+
+    #[test]
+    fn spec_errors_name_the_problem() {
+        assert!(parse_error("nope").contains("unknown criteria 'nope'"));
+        assert!(parse_error("nope(less-than)").contains("unknown combinator 'nope'"));
+        assert!(parse_error("all(less-than").contains("expected ',' or ')'"));
+        assert!(parse_error("weighted(zero-bytes)").contains("expected a number"));
+        assert!(parse_error("weighted(2 zero-bytes)").contains("<weight>*<criteria>"));
+        assert!(parse_error("zero-bytes>=many").contains("expected a number"));
+        assert!(parse_error("zero-bytes less-than").contains("unexpected 'less-than'"));
+        assert!(parse_error("prefix:xyz").contains("not a hex prefix"));
+    }
+
+    #[test]
+    fn submitted_specs_only_read_wordlists_by_name() {
+        let error =
+            |spec: &str, dir: Option<&Path>| match parse_submitted_criteria(spec, false, dir) {
+                Ok(_) => panic!("'{}' parsed", spec),
+                Err(e) => e,
+            };
+        assert!(error("words:/etc/passwd", None).contains("aren't allowed"));
+        let dir = Some(Path::new("/nonexistent"));
+        assert!(error("words:/etc/passwd", dir).contains("not a wordlist name"));
+        assert!(error("any(less-than, words-end:../passwd)", dir).contains("not a wordlist name"));
+        assert!(error("words:english.txt", dir).contains("/nonexistent/english.txt"));
+    }
+}
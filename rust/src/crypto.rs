@@ -1,17 +1,109 @@
 use bip32::{
     secp256k1::ecdsa::{SigningKey, VerifyingKey},
-    ExtendedPrivateKey, ExtendedPublicKey, Language, Mnemonic, Seed, XPrv,
+    DerivationPath, ExtendedPrivateKey, ExtendedPublicKey, Language, Mnemonic, Seed, XPrv,
 };
+use k256::elliptic_curve::sec1::ToEncodedPoint;
 use num_bigint::BigInt;
 use num_traits::One;
+use sha3::{Digest, Keccak256};
 use tiny_keccak::Hasher;
+use zeroize::Zeroizing;
 
 use crate::randnum::Entropy;
 
 pub trait AddressGenerator {
-    fn generate(&self, entropy: Entropy) -> Result<String, bip32::Error>;
+    fn generate(&self, entropy: &Entropy) -> Result<String, bip32::Error>;
 
-    fn clone_box(&self) -> Box<dyn AddressGenerator>;
+    /// What a user needs to take control of the address, e.g. the mnemonic phrase.
+    fn secret(&self, entropy: &Entropy) -> Zeroizing<String>;
+
+    /// The same address as `generate`, rebuilt along an independent, slower path
+    /// (the curve library's own point encoding and a second Keccak implementation)
+    /// to check a result before it is trusted.
+    fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error>;
+
+    /// Short name of the generator for saved results, e.g. `mnemonic`.
+    fn kind(&self) -> &'static str;
+
+    /// What besides the secret the address derives from, labelled for saved
+    /// results: the derivation path, the CREATE2 salt, ...
+    fn derivation(&self, entropy: &Entropy) -> (&'static str, Zeroizing<String>);
+
+    fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync>;
+}
+
+impl AddressGenerator for Box<dyn AddressGenerator + Send + Sync> {
+    fn generate(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        (**self).generate(entropy)
+    }
+
+    fn secret(&self, entropy: &Entropy) -> Zeroizing<String> {
+        (**self).secret(entropy)
+    }
+
+    fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        (**self).reference(entropy)
+    }
+
+    fn kind(&self) -> &'static str {
+        (**self).kind()
+    }
+
+    fn derivation(&self, entropy: &Entropy) -> (&'static str, Zeroizing<String>) {
+        (**self).derivation(entropy)
+    }
+
+    fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync> {
+        (**self).clone_box()
+    }
+}
+
+impl Clone for Box<dyn AddressGenerator + Send + Sync> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The first account of a phrase, which is the address the search ranks.
+pub const DEFAULT_PATH: &str = "m/44'/60'/0'/0/0";
+
+/// Parse a phrase language given by name. `bip32` only ships the English word
+/// list, so that is the only name accepted for now.
+pub fn parse_language(s: &str) -> Result<Language, String> {
+    match s {
+        "english" => Ok(Language::English),
+        _ => Err(format!(
+            "unknown language '{}', expected english (the only word list bip32 supports)",
+            s
+        )),
+    }
+}
+
+/// Parse a phrase read back from the user or a results file. `bip32` only
+/// handles 24-word phrases and rejects any other length with a bare "bip39
+/// error", so the word count is checked first.
+pub fn parse_phrase(phrase: &str, language: Language) -> Result<Mnemonic, String> {
+    let words = phrase.split_whitespace().count();
+    if words != 24 {
+        return Err(format!(
+            "the phrase has {} words, but only 24-word phrases are supported",
+            words
+        ));
+    }
+    Mnemonic::new(phrase, language).map_err(|e| format!("invalid mnemonic phrase: {}", e))
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hashed = [0u8; 32];
+    let mut keccak = tiny_keccak::Keccak::v256();
+    keccak.update(data);
+    keccak.finalize(&mut hashed);
+    hashed
+}
+
+/// The `0x`-prefixed lowercase address: the last 20 bytes of a Keccak-256 hash.
+pub fn hash_to_address(hashed: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(&hashed[12..]))
 }
 
 #[derive(Copy, Clone)]
@@ -19,33 +111,120 @@ pub struct MnemonicAddressGenerator {
     pub language: Language,
 }
 
-impl AddressGenerator for MnemonicAddressGenerator {
-    fn generate(&self, entropy: Entropy) -> Result<String, bip32::Error> {
-        // Generate random Mnemonic using the specified language
-        let mnemonic = Mnemonic::from_entropy(entropy, self.language);
+impl MnemonicAddressGenerator {
+    /// The address a phrase's first account derives to, as the search finds it.
+    pub fn address(&self, mnemonic: &Mnemonic) -> Result<String, bip32::Error> {
+        self.address_at(mnemonic, &DEFAULT_PATH.parse()?)
+    }
 
+    /// The address at `path` of a phrase without a passphrase.
+    pub fn address_at(
+        &self,
+        mnemonic: &Mnemonic,
+        path: &DerivationPath,
+    ) -> Result<String, bip32::Error> {
         // Derive a BIP39 seed value using the empty password
         let seed: Seed = mnemonic.to_seed("");
 
         // Derive Ethereum address from seed
-        let child_xprv: ExtendedPrivateKey<SigningKey> =
-            XPrv::derive_from_path(&seed, &"m/44'/60'/0'/0/0".parse()?)?;
+        let child_xprv: ExtendedPrivateKey<SigningKey> = XPrv::derive_from_path(&seed, path)?;
         let child_xpub: ExtendedPublicKey<VerifyingKey> = child_xprv.public_key();
         let verifying_key: &VerifyingKey = child_xpub.public_key();
         let uncompressed_pubkey = decompress_pubkey(&verifying_key.to_bytes());
 
-        let mut hashed_pubkey = [0u8; 32];
+        Ok(hash_to_address(&keccak256(&uncompressed_pubkey)))
+    }
+}
+
+impl AddressGenerator for MnemonicAddressGenerator {
+    fn generate(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        // Generate random Mnemonic using the specified language
+        let mnemonic = Mnemonic::from_entropy(*entropy, self.language);
+        self.address(&mnemonic)
+    }
+
+    fn secret(&self, entropy: &Entropy) -> Zeroizing<String> {
+        let mnemonic = Mnemonic::from_entropy(*entropy, self.language);
+        Zeroizing::new(mnemonic.phrase().to_string())
+    }
+
+    fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        let mnemonic = Mnemonic::from_entropy(*entropy, self.language);
+        let seed: Seed = mnemonic.to_seed("");
+        let child_xprv: ExtendedPrivateKey<SigningKey> =
+            XPrv::derive_from_path(&seed, &DEFAULT_PATH.parse()?)?;
+
+        // k256's own SEC1 encoding instead of `decompress_pubkey`
+        let secret_key = k256::SecretKey::from_be_bytes(&child_xprv.private_key().to_bytes())
+            .map_err(|_| bip32::Error::Crypto)?;
+        let uncompressed = secret_key.public_key().to_encoded_point(false);
+
+        // sha3's Keccak-256 instead of tiny-keccak, skipping the 0x04 tag byte
+        let hashed = Keccak256::digest(&uncompressed.as_bytes()[1..]);
+        Ok(format!("0x{}", hex::encode(&hashed[12..])))
+    }
+
+    fn kind(&self) -> &'static str {
+        "mnemonic"
+    }
+
+    fn derivation(&self, _entropy: &Entropy) -> (&'static str, Zeroizing<String>) {
+        ("path", Zeroizing::new(DEFAULT_PATH.to_string()))
+    }
+
+    fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync> {
+        Box::new(*self)
+    }
+}
+
+/// Contract addresses deployed with `CREATE2`, using the entropy as the salt:
+/// `keccak256(0xff ++ deployer ++ salt ++ keccak256(init_code))[12..]`.
+#[derive(Copy, Clone)]
+pub struct Create2AddressGenerator {
+    pub deployer: [u8; 20],
+    pub init_code_hash: [u8; 32],
+}
+
+impl AddressGenerator for Create2AddressGenerator {
+    fn generate(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        let mut hashed = [0u8; 32];
         let mut keccak = tiny_keccak::Keccak::v256();
-        keccak.update(&uncompressed_pubkey);
-        keccak.finalize(&mut hashed_pubkey);
+        keccak.update(&[0xff]);
+        keccak.update(&self.deployer);
+        keccak.update(entropy);
+        keccak.update(&self.init_code_hash);
+        keccak.finalize(&mut hashed);
 
-        let address_bytes = &hashed_pubkey[12..];
-        let address = format!("0x{}", hex::encode(address_bytes));
+        Ok(hash_to_address(&hashed))
+    }
 
-        Ok(address)
+    /// The salt to pass to the deployer.
+    fn secret(&self, entropy: &Entropy) -> Zeroizing<String> {
+        Zeroizing::new(format!("0x{}", hex::encode(entropy)))
     }
 
-    fn clone_box(&self) -> Box<dyn AddressGenerator> {
+    fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+        let mut preimage = Vec::with_capacity(85);
+        preimage.push(0xff);
+        preimage.extend_from_slice(&self.deployer);
+        preimage.extend_from_slice(entropy);
+        preimage.extend_from_slice(&self.init_code_hash);
+        let hashed = Keccak256::digest(&preimage);
+        Ok(format!("0x{}", hex::encode(&hashed[12..])))
+    }
+
+    fn kind(&self) -> &'static str {
+        "create2"
+    }
+
+    fn derivation(&self, entropy: &Entropy) -> (&'static str, Zeroizing<String>) {
+        (
+            "salt",
+            Zeroizing::new(format!("0x{}", hex::encode(entropy))),
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync> {
         Box::new(*self)
     }
 }
@@ -65,7 +244,7 @@ impl AddressGenerator for MnemonicAddressGenerator {
 //  ```
 //
 // Rust implementation:
-fn decompress_pubkey(compressed_pubkey: &[u8]) -> Vec<u8> {
+pub fn decompress_pubkey(compressed_pubkey: &[u8]) -> Vec<u8> {
     // p = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F
     let p = BigInt::parse_bytes(
         b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F",
@@ -85,14 +264,50 @@ fn decompress_pubkey(compressed_pubkey: &[u8]) -> Vec<u8> {
 
     // if y % 2 != public_key[0] % 2:
     //     y = p - y
-    let mut y_bytes = y.to_bytes_be().1;
-    if y.clone() % 2 != (compressed_pubkey[0] % 2).into() {
-        y_bytes = (p.clone() - y).to_bytes_be().1;
-    }
+    let y = if y.clone() % 2 != (compressed_pubkey[0] % 2).into() {
+        p - y
+    } else {
+        y
+    };
 
     // y = y.to_bytes(32, byteorder='big')
     // return (public_key[1:33] + y).hex()
-    [&compressed_pubkey[1..33], &y_bytes].concat()
+    // to_bytes_be drops leading zeros, so pad y back out to 32 bytes
+    let y_bytes = y.to_bytes_be().1;
+    let mut uncompressed = vec![0u8; 64];
+    uncompressed[..32].copy_from_slice(&compressed_pubkey[1..33]);
+    uncompressed[64 - y_bytes.len()..].copy_from_slice(&y_bytes);
+    uncompressed
+}
+
+/// EIP-55 mixed-case checksum encoding of a `0x`-prefixed hex address.
+///
+/// A letter is upper-cased when the matching nibble of `keccak256(lowercase hex)` is >= 8.
+pub fn to_checksum_address(address: &str) -> String {
+    let hex = address.trim_start_matches("0x").to_ascii_lowercase();
+
+    let mut hash = [0u8; 32];
+    let mut keccak = tiny_keccak::Keccak::v256();
+    keccak.update(hex.as_bytes());
+    keccak.finalize(&mut hash);
+
+    let checksummed: String = hex
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", checksummed)
 }
 
 // pub struct MnemonicAddress {
@@ -134,3 +349,64 @@ fn decompress_pubkey(compressed_pubkey: &[u8]) -> Vec<u8> {
 
 //     Ok(MnemonicAddress { mnemonic, address })
 // }
+
+#[cfg(test)]
+mod tests {
+    use k256::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn decompress_pads_a_short_y_coordinate() {
+        // about one key in 256 has a y coordinate starting with a zero byte
+        let (compressed, uncompressed) = (1u64..)
+            .map(|n| {
+                let mut bytes = [0u8; 32];
+                bytes[24..].copy_from_slice(&n.to_be_bytes());
+                SecretKey::from_be_bytes(&bytes).unwrap().public_key()
+            })
+            .map(|key| (key.to_encoded_point(true), key.to_encoded_point(false)))
+            .find(|(_, uncompressed)| uncompressed.as_bytes()[33] == 0)
+            .unwrap();
+        assert_eq!(
+            decompress_pubkey(compressed.as_bytes()),
+            &uncompressed.as_bytes()[1..]
+        );
+    }
+
+    #[test]
+    fn only_24_word_phrases_parse() {
+        let twelve = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let error = parse_phrase(twelve, Language::English).err();
+        assert_eq!(
+            error.as_deref(),
+            Some("the phrase has 12 words, but only 24-word phrases are supported")
+        );
+
+        let phrase = Mnemonic::from_entropy([0; 32], Language::English);
+        assert!(parse_phrase(phrase.phrase(), Language::English).is_ok());
+    }
+
+    #[test]
+    fn checksum_addresses_match_the_eip55_vectors() {
+        for expected in [
+            // all caps
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+            // all lower
+            "0xde709f2102306220921060314715629080e2fb77",
+            "0x27b1fdb04752bbc536007a920d24acb045561c26",
+            // mixed
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert_eq!(to_checksum_address(&expected.to_lowercase()), expected);
+            assert_eq!(
+                to_checksum_address(&expected.to_uppercase().replace("0X", "0x")),
+                expected
+            );
+        }
+    }
+}
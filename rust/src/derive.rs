@@ -0,0 +1,148 @@
+use std::{
+    io::{self, BufRead},
+    ops::Range,
+    str::FromStr,
+};
+
+use bip32::{
+    secp256k1::ecdsa::{SigningKey, VerifyingKey},
+    ChildNumber, DerivationPath, ExtendedPrivateKey, ExtendedPublicKey, Language, XPrv,
+};
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::crypto::{
+    decompress_pubkey, hash_to_address, keccak256, parse_phrase, to_checksum_address, DEFAULT_PATH,
+};
+
+/// Account level of the path the search uses, without the address index.
+pub fn default_account_path() -> DerivationPath {
+    DEFAULT_PATH
+        .parse::<DerivationPath>()
+        .ok()
+        .and_then(|path| path.parent())
+        .expect("default path has an address index")
+}
+
+/// Read the next non-empty line of stdin, e.g. a mnemonic phrase, with its
+/// words separated by single spaces.
+pub fn read_phrase(input: &mut impl BufRead) -> io::Result<Zeroizing<String>> {
+    let mut line = Zeroizing::new(String::new());
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no mnemonic phrase on stdin",
+            ));
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if !words.is_empty() {
+            return Ok(Zeroizing::new(words.join(" ")));
+        }
+    }
+}
+
+/// Address indices to derive, written `<n>` or `<start>..<end>` (end excluded).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexRange(pub Range<u32>);
+
+impl Default for IndexRange {
+    fn default() -> Self {
+        Self(0..1)
+    }
+}
+
+impl FromStr for IndexRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("expected '<n>' or '<start>..<end>', got '{}'", s))
+        };
+        let range = match s.split_once("..") {
+            Some((start, end)) => parse(start)?..parse(end)?,
+            None => {
+                let index = parse(s)?;
+                index..index.saturating_add(1)
+            }
+        };
+        if range.is_empty() {
+            return Err(format!("index range '{}' is empty", s));
+        }
+        Ok(Self(range))
+    }
+}
+
+/// Print every value from a mnemonic phrase on stdin to its addresses:
+/// seed, private key, public key, uncompressed public key, Keccak-256 and address.
+///
+/// With `passphrase`, the BIP-39 passphrase is read from the line after the
+/// phrase, so it never shows up in the shell history or process list.
+pub fn run(
+    language: Language,
+    passphrase: bool,
+    account_path: &DerivationPath,
+    indices: &IndexRange,
+    as_json: bool,
+) -> Result<(), String> {
+    let mut stdin = io::stdin().lock();
+    let phrase = read_phrase(&mut stdin).map_err(|e| e.to_string())?;
+    let mnemonic = parse_phrase(phrase.as_str(), language)?;
+    let password = if passphrase {
+        let mut line = Zeroizing::new(String::new());
+        stdin.read_line(&mut line).map_err(|e| e.to_string())?;
+        Zeroizing::new(line.trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        Zeroizing::new(String::new())
+    };
+    let seed = mnemonic.to_seed(&password);
+
+    if !as_json {
+        println!("Phrase       : {}", mnemonic.phrase());
+        println!(
+            "Passphrase   : {}",
+            if passphrase { "(from stdin)" } else { "(none)" }
+        );
+        println!("Seed         : 0x{}", hex::encode(seed.as_bytes()));
+    }
+    for index in indices.0.clone() {
+        let mut path = account_path.clone();
+        path.push(ChildNumber::new(index, false).map_err(|e| e.to_string())?);
+
+        let child_xprv: ExtendedPrivateKey<SigningKey> =
+            XPrv::derive_from_path(&seed, &path).map_err(|e| e.to_string())?;
+        let private_key = Zeroizing::new(hex::encode(child_xprv.private_key().to_bytes()));
+        let child_xpub: ExtendedPublicKey<VerifyingKey> = child_xprv.public_key();
+        let public_key = child_xpub.public_key().to_bytes();
+        let uncompressed = decompress_pubkey(&public_key);
+        let hashed = keccak256(&uncompressed);
+        let address = to_checksum_address(&hash_to_address(&hashed));
+
+        if as_json {
+            let value = json!({
+                "phrase": mnemonic.phrase(),
+                "passphrase": passphrase,
+                "seed": format!("0x{}", hex::encode(seed.as_bytes())),
+                "path": path.to_string(),
+                "private_key": format!("0x{}", *private_key),
+                "public_key": format!("0x{}", hex::encode(public_key)),
+                "uncompressed_public_key": format!("0x{}", hex::encode(&uncompressed)),
+                "keccak256": format!("0x{}", hex::encode(hashed)),
+                "address": address,
+            });
+            println!("{}", value);
+        } else {
+            println!();
+            println!("Path         : {}", path);
+            println!("Private Key  : 0x{}", *private_key);
+            println!("Public Key   : 0x{}", hex::encode(public_key));
+            println!("Uncompressed : 0x{}", hex::encode(&uncompressed));
+            println!("Keccak256    : 0x{}", hex::encode(hashed));
+            println!("Address      : {}", address);
+        }
+    }
+    Ok(())
+}
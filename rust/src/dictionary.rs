@@ -0,0 +1,213 @@
+use std::{fmt, fs, path::Path, str::FromStr, sync::Arc};
+
+use crate::criteria::{CriteriaPredicate, Score};
+
+/// Letters a hex digit can spell: `a`-`f` as themselves, and the leetspeak
+/// `0`=o, `1`=i/l, `2`=z, `3`=e, `4`=a, `5`=s, `6`/`9`=g, `7`=t, `8`=b.
+///
+/// Words and addresses are both reduced to these 12 symbols, one per letter
+/// class, so that a word is spelled exactly when the symbols agree.
+const SYMBOLS: usize = 12;
+
+fn letter_symbol(letter: u8) -> Option<u8> {
+    Some(match letter.to_ascii_lowercase() {
+        b'a' => 0,
+        b'b' => 1,
+        b'c' => 2,
+        b'd' => 3,
+        b'e' => 4,
+        b'f' => 5,
+        b'g' => 6,
+        b'i' | b'l' => 7,
+        b'o' => 8,
+        b's' => 9,
+        b't' => 10,
+        b'z' => 11,
+        _ => return None,
+    })
+}
+
+fn digit_symbol(digit: u8) -> Option<u8> {
+    match digit.to_ascii_lowercase() {
+        b'4' => Some(0),
+        b'8' => Some(1),
+        b'3' => Some(4),
+        b'6' | b'9' => Some(6),
+        b'1' => Some(7),
+        b'0' => Some(8),
+        b'5' => Some(9),
+        b'7' => Some(10),
+        b'2' => Some(11),
+        letter @ b'a'..=b'f' => letter_symbol(letter),
+        _ => None,
+    }
+}
+
+/// A wordlist kept as a trie over hex-spellable symbols.
+pub struct Dictionary {
+    /// Children by symbol (0 = none) and the word ending at each node.
+    nodes: Vec<([u32; SYMBOLS], Option<String>)>,
+    words: usize,
+}
+
+/// The longest word found in an address.
+pub struct WordMatch<'a> {
+    pub word: &'a str,
+    /// Digits matched as the letters `a`-`f` themselves rather than leetspeak.
+    pub literal: usize,
+}
+
+impl Dictionary {
+    /// Load one word per line, skipping words that hex can't spell.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read wordlist {}: {}", path.display(), e))?;
+        let mut dictionary = Self {
+            nodes: vec![([0; SYMBOLS], None)],
+            words: 0,
+        };
+        for word in contents.lines().map(str::trim) {
+            dictionary.insert(word);
+        }
+        if dictionary.words == 0 {
+            return Err(format!(
+                "wordlist {} has no hex-spellable words",
+                path.display()
+            ));
+        }
+        Ok(dictionary)
+    }
+
+    fn insert(&mut self, word: &str) {
+        let Some(symbols) = word.bytes().map(letter_symbol).collect::<Option<Vec<u8>>>() else {
+            return;
+        };
+        if symbols.is_empty() || symbols.len() > 40 {
+            return;
+        }
+        let mut node = 0;
+        for symbol in symbols {
+            let next = self.nodes[node].0[symbol as usize];
+            node = if next == 0 {
+                self.nodes.push(([0; SYMBOLS], None));
+                let next = self.nodes.len() - 1;
+                self.nodes[node].0[symbol as usize] = next as u32;
+                next
+            } else {
+                next as usize
+            };
+        }
+        if self.nodes[node].1.is_none() {
+            self.words += 1;
+            self.nodes[node].1 = Some(word.to_ascii_lowercase());
+        }
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.words
+    }
+
+    /// The longest word spelled by `hex` starting at its first digit, with
+    /// how many digits into `hex` it ends.
+    fn longest_from(&self, hex: &[u8], must_end: bool) -> Option<(&str, usize)> {
+        let mut node = 0;
+        let mut found = None;
+        for (i, digit) in hex.iter().enumerate() {
+            let Some(symbol) = digit_symbol(*digit) else {
+                break;
+            };
+            node = self.nodes[node].0[symbol as usize] as usize;
+            if node == 0 {
+                break;
+            }
+            if let Some(word) = &self.nodes[node].1 {
+                if !must_end || i + 1 == hex.len() {
+                    found = Some((word.as_str(), i + 1));
+                }
+            }
+        }
+        found
+    }
+
+    /// The longest word at `position` in the address, preferring fewer
+    /// leetspeak digits between words of the same length.
+    pub fn longest_word(&self, address: &str, position: WordPosition) -> Option<WordMatch<'_>> {
+        let hex = address.trim_start_matches("0x").as_bytes();
+        let starts = match position {
+            WordPosition::Start => 0..1,
+            WordPosition::End | WordPosition::Anywhere => 0..hex.len(),
+        };
+        starts
+            .filter_map(|start| {
+                let (word, len) =
+                    self.longest_from(&hex[start..], position == WordPosition::End)?;
+                let literal = hex[start..start + len]
+                    .iter()
+                    .filter(|d| d.is_ascii_alphabetic())
+                    .count();
+                Some(WordMatch { word, literal })
+            })
+            .max_by_key(|m| (m.word.len(), m.literal))
+    }
+}
+
+/// Where in the address a dictionary word has to sit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WordPosition {
+    Start,
+    End,
+    Anywhere,
+}
+
+impl FromStr for WordPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
+            "anywhere" => Ok(Self::Anywhere),
+            _ => Err(format!(
+                "unknown word position '{}', expected start, end or anywhere",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for WordPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Start => write!(f, "start"),
+            Self::End => write!(f, "end"),
+            Self::Anywhere => write!(f, "anywhere"),
+        }
+    }
+}
+
+/// Prefer addresses spelling a longer dictionary word at `position`, counting
+/// leetspeak digits as letters; ties go to fewer leetspeak digits.
+#[derive(Clone)]
+pub struct DictionaryCriteria {
+    pub dictionary: Arc<Dictionary>,
+    pub position: WordPosition,
+}
+
+impl DictionaryCriteria {
+    pub fn longest_word<'a>(&'a self, address: &str) -> Option<WordMatch<'a>> {
+        self.dictionary.longest_word(address, self.position)
+    }
+}
+
+impl CriteriaPredicate for DictionaryCriteria {
+    fn score(&self, address: &str) -> Score {
+        let (len, literal) = self
+            .longest_word(address)
+            .map_or((0, 0), |m| (m.word.len(), m.literal));
+        Score::new([len as u64, literal as u64])
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    fmt, fs,
+    io::{self, BufRead, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+/// Rolls needed for 256 bits of entropy from a six-sided die: ceil(256 / log2(6)).
+const RECOMMENDED_DICE_ROLLS: usize = 99;
+
+/// Where to read user-supplied entropy from, written `dice`, `stdin` or `file:<path>`.
+#[derive(Clone, Debug)]
+pub enum EntropySource {
+    /// Dice rolls typed in on stdin, one or more per line, ending with an empty line.
+    Dice,
+    /// Raw bytes of a file.
+    File(PathBuf),
+    /// Raw bytes read from stdin until EOF.
+    Stdin,
+}
+
+impl FromStr for EntropySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dice" => Ok(Self::Dice),
+            "stdin" => Ok(Self::Stdin),
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(Self::File(PathBuf::from(path))),
+                _ => Err(format!(
+                    "expected 'dice', 'stdin' or 'file:<path>', got '{}'",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for EntropySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dice => write!(f, "dice"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Stdin => write!(f, "stdin"),
+        }
+    }
+}
+
+impl EntropySource {
+    pub fn read(&self) -> io::Result<Zeroizing<Vec<u8>>> {
+        let secret = match self {
+            Self::Dice => read_dice(io::stdin().lock())?,
+            Self::File(path) => Zeroizing::new(fs::read(path)?),
+            Self::Stdin => {
+                let mut secret = Zeroizing::new(Vec::new());
+                io::stdin().lock().read_to_end(&mut secret)?;
+                secret
+            }
+        };
+        if secret.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no extra entropy read from {}", self),
+            ));
+        }
+        Ok(secret)
+    }
+}
+
+fn read_dice(input: impl BufRead) -> io::Result<Zeroizing<Vec<u8>>> {
+    eprintln!(
+        "Enter dice rolls (1-6), at least {} of them, then an empty line:",
+        RECOMMENDED_DICE_ROLLS
+    );
+    let mut rolls = Zeroizing::new(Vec::new());
+    for line in input.lines() {
+        let line = Zeroizing::new(line?);
+        if line.trim().is_empty() {
+            break;
+        }
+        for c in line.chars().filter(|c| !c.is_whitespace()) {
+            match c {
+                '1'..='6' => rolls.push(c as u8),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("'{}' is not a dice roll", c),
+                    ))
+                }
+            }
+        }
+    }
+    if rolls.len() < RECOMMENDED_DICE_ROLLS {
+        log::warn!(
+            "Only {} dice rolls entered; {} are needed for 256 bits of entropy",
+            rolls.len(),
+            RECOMMENDED_DICE_ROLLS
+        );
+    }
+    Ok(rolls)
+}
+
+/// Short, non-reversible identifier for a secret, safe to record in run metadata.
+pub fn fingerprint(secret: &[u8]) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"vanitygen extra entropy fingerprint")
+        .chain_update(secret)
+        .finalize();
+    hex::encode(&digest[..8])
+}
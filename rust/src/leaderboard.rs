@@ -0,0 +1,156 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use k256::PublicKey;
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{to_checksum_address, AddressGenerator},
+    seal,
+    search::SearchResult,
+};
+
+/// Where the leaderboard, including every secret on it, is kept between updates.
+pub const LEADERBOARD_FILE: &str = "/var/log/vanitygen/leaderboard.log";
+
+/// The best `capacity` distinct results seen so far, best first.
+pub struct Leaderboard {
+    capacity: usize,
+    entries: Vec<SearchResult>,
+}
+
+impl Leaderboard {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Vec::with_capacity(capacity.max(1)),
+        }
+    }
+
+    /// Insert `result` if it makes the board, returning its rank (0 = new best).
+    ///
+    /// Addresses already on the board are ignored, and the worst entry is
+    /// dropped (and wiped) once the board is over capacity. Equal scores rank
+    /// by address, so the board doesn't depend on the order results arrive in.
+    pub fn offer(&mut self, result: SearchResult) -> Option<usize> {
+        if self.entries.iter().any(|e| e.address == result.address) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| {
+                result.score > e.score || (result.score == e.score && result.address < e.address)
+            })
+            .unwrap_or(self.entries.len());
+        if rank >= self.capacity {
+            return None;
+        }
+        self.entries.insert(rank, result);
+        self.entries.truncate(self.capacity);
+        Some(rank)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.capacity
+    }
+
+    pub fn best(&self) -> Option<&SearchResult> {
+        self.entries.first()
+    }
+
+    /// The entry a newcomer has to beat once the board is full.
+    pub fn worst(&self) -> Option<&SearchResult> {
+        self.entries.last()
+    }
+
+    pub fn entries(&self) -> &[SearchResult] {
+        &self.entries
+    }
+
+    /// The entry whose EIP-55 checksum casing looks the most regular.
+    ///
+    /// Fewer switches between upper and lower case letters reads as nicer;
+    /// ties go to the better-ranked entry.
+    pub fn nicest(&self) -> Option<&SearchResult> {
+        self.entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(rank, e)| (case_changes(&to_checksum_address(&e.address)), *rank))
+            .map(|(_, e)| e)
+    }
+
+    /// The full board, secrets included, as written to the leaderboard file.
+    ///
+    /// With `sealed_to`, each secret is sealed to that key instead, for
+    /// `results open` to read back.
+    pub fn snapshot<A: AddressGenerator>(
+        &self,
+        address_generator: &A,
+        sealed_to: Option<&PublicKey>,
+    ) -> Zeroizing<String> {
+        // sized up front so that growing it leaves no unwiped copies of the secrets
+        let mut snapshot = Zeroizing::new(String::with_capacity(self.entries.len() * 640));
+        for (rank, entry) in self.entries.iter().enumerate() {
+            let secret = address_generator.secret(&entry.seed);
+            let _ = match sealed_to {
+                Some(key) => writeln!(
+                    *snapshot,
+                    "{} {} {}",
+                    rank + 1,
+                    to_checksum_address(&entry.address),
+                    seal::seal(key, secret.as_bytes())
+                ),
+                None => writeln!(
+                    *snapshot,
+                    "{} {} {}",
+                    rank + 1,
+                    to_checksum_address(&entry.address),
+                    *secret
+                ),
+            };
+        }
+        snapshot
+    }
+}
+
+/// Rewrite `path` with a board `snapshot`, readable only by the owner.
+///
+/// Takes the snapshot rather than the board so that the write and sync happen
+/// without holding the board's lock.
+pub fn persist(path: &Path, snapshot: &str) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        file.write_all(snapshot.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Number of times consecutive letters switch case, ignoring digits in between.
+fn case_changes(address: &str) -> usize {
+    let mut letters = address
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.is_ascii_uppercase());
+    let mut changes = 0;
+    if let Some(mut previous) = letters.next() {
+        for upper in letters {
+            if upper != previous {
+                changes += 1;
+            }
+            previous = upper;
+        }
+    }
+    changes
+}
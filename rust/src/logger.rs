@@ -1,17 +1,27 @@
-use log::LevelFilter;
+use std::{fmt, str::FromStr};
+
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    LevelFilter,
+};
 use log4rs::filter::Filter;
 use log4rs::{
     append::{console::ConsoleAppender, file::FileAppender},
     config::{Appender, Config, Logger, Root},
-    encode::pattern::PatternEncoder,
+    encode::{self, pattern::PatternEncoder, Encode},
 };
+use serde_json::{json, Map};
+
+/// Where `mnemonic_log!` writes saved results, one `<address> <phrase>` per line.
+pub const MNEMONICS_FILE: &str = "/var/log/vanitygen/mnemonics.log";
 
 #[derive(Debug)]
 struct ExcludeMnemonicLogger;
 
 impl Filter for ExcludeMnemonicLogger {
     fn filter(&self, record: &log::Record) -> log4rs::filter::Response {
-        if record.target() == "mnemonic_logger" {
+        // coverage ranges are as secret as the phrases: they reveal the entropy
+        if record.target() == "mnemonic_logger" || record.target() == "coverage_logger" {
             log4rs::filter::Response::Reject
         } else {
             log4rs::filter::Response::Neutral
@@ -19,22 +29,113 @@ impl Filter for ExcludeMnemonicLogger {
     }
 }
 
-pub fn setup_logger() -> Result<(), Box<dyn std::error::Error>> {
+/// How progress is written to the terminal and `vanitygen.log`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `INFO - <message>` lines for people.
+    #[default]
+    Text,
+    /// One JSON object per line for log shippers, with time, level, message and
+    /// the record's fields, e.g. `job`, `attempts`, `address` and `score`.
+    Json,
+}
+
+impl LogFormat {
+    fn encoder(self) -> Box<dyn Encode> {
+        match self {
+            Self::Text => Box::new(PatternEncoder::new("{l} - {m}\n")),
+            Self::Json => Box::new(JsonLinesEncoder),
+        }
+    }
+}
+
+/// Like log4rs' `JsonEncoder`, but keeps the record's fields typed: numbers
+/// stay numbers instead of being written as strings.
+#[derive(Debug)]
+struct JsonLinesEncoder;
+
+struct Attributes(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for Attributes {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(n) = value.to_u64() {
+            json!(n)
+        } else if let Some(n) = value.to_i64() {
+            json!(n)
+        } else if let Some(x) = value.to_f64() {
+            json!(x)
+        } else if let Some(b) = value.to_bool() {
+            json!(b)
+        } else if let Some(s) = value.to_borrowed_str() {
+            json!(s)
+        } else {
+            json!(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
+impl Encode for JsonLinesEncoder {
+    fn encode(&self, w: &mut dyn encode::Write, record: &log::Record) -> anyhow::Result<()> {
+        let mut attributes = Attributes(Map::new());
+        record.key_values().visit(&mut attributes)?;
+        let line = json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "message": record.args().to_string(),
+            "module_path": record.module_path(),
+            "file": record.file(),
+            "line": record.line(),
+            "target": record.target(),
+            "thread": std::thread::current().name(),
+            "attributes": attributes.0,
+        });
+        serde_json::to_writer(&mut *w, &line)?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format '{}', expected text or json", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+pub fn setup_logger(format: LogFormat) -> Result<(), Box<dyn std::error::Error>> {
     // Set up terminal appender
-    let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new("{l} - {m}\n")))
-        .build();
+    let stdout = ConsoleAppender::builder().encoder(format.encoder()).build();
 
     // Set up log file appender
     let log_file = FileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new("{l} - {m}\n")))
-        .build("/var/log/vanitygen/vanitygen.log")
-        .unwrap();
+        .encoder(format.encoder())
+        .build("/var/log/vanitygen/vanitygen.log")?;
 
     // Set up a second log file appender
     let mnemonic_log = FileAppender::builder()
         .encoder(Box::new(PatternEncoder::new("{m}\n")))
-        .build("/var/log/vanitygen/mnemonics.log")?;
+        .build(MNEMONICS_FILE)?;
+
+    // Set up a log file appender for covered entropy ranges
+    let coverage_log = FileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new("{m}\n")))
+        .build("/var/log/vanitygen/coverage.log")?;
 
     // Create logging configuration
     let config = Config::builder()
@@ -49,11 +150,17 @@ pub fn setup_logger() -> Result<(), Box<dyn std::error::Error>> {
                 .build("log_file", Box::new(log_file)),
         )
         .appender(Appender::builder().build("mnemonic_log", Box::new(mnemonic_log)))
+        .appender(Appender::builder().build("coverage_log", Box::new(coverage_log)))
         .logger(
             Logger::builder()
                 .appender("mnemonic_log")
                 .build("mnemonic_logger", LevelFilter::Info),
         )
+        .logger(
+            Logger::builder()
+                .appender("coverage_log")
+                .build("coverage_logger", LevelFilter::Info),
+        )
         .build(
             Root::builder()
                 .appender("stdout")
@@ -67,6 +174,16 @@ pub fn setup_logger() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Log to the terminal only, for modes that never write mnemonics or coverage.
+pub fn setup_console_logger(format: LogFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = ConsoleAppender::builder().encoder(format.encoder()).build();
+    let config = Config::builder()
+        .appender(Appender::builder().build("stdout", Box::new(stdout)))
+        .build(Root::builder().appender("stdout").build(LevelFilter::Info))?;
+    log4rs::init_config(config)?;
+    Ok(())
+}
+
 #[macro_export]
 macro_rules! mnemonic_log {
     ($($arg:tt)+) => {
@@ -82,3 +199,19 @@ macro_rules! mnemonic_log {
         );
     };
 }
+
+#[macro_export]
+macro_rules! coverage_log {
+    ($($arg:tt)+) => {
+        log::logger().log(
+            &log::Record::builder()
+                .level(log::Level::Info)
+                .target("coverage_logger")
+                .module_path(Some(module_path!()))
+                .file(Some(file!()))
+                .line(Some(line!()))
+                .args(format_args!($($arg)+))
+                .build()
+        );
+    };
+}
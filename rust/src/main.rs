@@ -1,49 +1,641 @@
-use bip32::Language;
-use clap::Parser;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bip32::{DerivationPath, Language};
+use clap::{
+    error::ErrorKind, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
 use num_format::{Locale, ToFormattedString};
+use rayon::ThreadPoolBuilder;
 
 use crate::{
-    criteria::{CriteriaPredicate, LessThanCriteria},
-    crypto::{AddressGenerator, MnemonicAddressGenerator},
-    logger::setup_logger,
-    randnum::{NumberGenerator, RandNumberGenerator},
-    search::ThreadPoolSearcher,
+    aesthetic::{
+        DigitRunCriteria, LetterRunCriteria, MirrorCriteria, PalindromeCriteria,
+        RepeatedEndsCriteria, RepeatedRunCriteria,
+    },
+    config::{effective_config, take_unset_options, Profile, CONFIG_FILE},
+    criteria::{
+        calldata_gas_savings, parse_criteria, CriteriaPredicate, HookFlags, HookFlagsCriteria,
+        LeadingZeroBytesCriteria, LessThanCriteria, PrefixCriteria, ZeroBytesCriteria,
+    },
+    crypto::{
+        parse_language, to_checksum_address, AddressGenerator, Create2AddressGenerator,
+        MnemonicAddressGenerator, DEFAULT_PATH,
+    },
+    derive::{default_account_path, IndexRange},
+    dictionary::{Dictionary, DictionaryCriteria, WordPosition},
+    entropy::{fingerprint, EntropySource},
+    leaderboard::{Leaderboard, LEADERBOARD_FILE},
+    logger::{setup_console_logger, setup_logger, LogFormat, MNEMONICS_FILE},
+    pattern::PatternCriteria,
+    randnum::{
+        CounterNumberGenerator, MixedNumberGenerator, NumberGenerator, RandNumberGenerator,
+        SecretEntropy, SeededNumberGenerator, Shard,
+    },
+    results::{Filter, RESULTS_DB},
+    save::{RunInfo, SaveRule, Saver, SinkSpec},
+    seal::{format_public_key, parse_public_key, read_secret_key, write_secret_key},
+    search::{SearchProgress, ThreadPoolSearcher},
+    verify::Verifier,
 };
 
+mod aesthetic;
+mod bench;
+mod config;
 mod criteria;
 mod crypto;
+mod derive;
+mod dictionary;
+mod entropy;
+mod leaderboard;
 mod logger;
+mod metrics;
+mod pattern;
 mod randnum;
+mod results;
+mod save;
+mod seal;
 mod search;
-use log::info;
+mod secret;
+mod serve;
+mod verify;
+use log::{info, warn};
+use zeroize::Zeroizing;
 
-/// Simple program to greet a person
+/// Find vanity Ethereum addresses: mnemonic phrases or CREATE2 salts whose
+/// address ranks best under the chosen criteria.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)] // parsed once per run
+enum Command {
+    /// search for the addresses that rank best under the chosen criteria
+    Search(SearchArgs),
+    /// show every step from a mnemonic phrase read from stdin to its addresses
+    Derive(DeriveArgs),
+    /// re-derive every address in mnemonics.log or another results file
+    Verify(VerifyArgs),
+    /// estimate how many attempts, and how long, a full match takes
+    Estimate(EstimateArgs),
+    /// benchmark the generic search pipeline against boxed trait objects
+    Bench(BenchArgs),
+    /// list, filter and export results saved to a SQLite database
+    Results(ResultsArgs),
+    /// run searches submitted over a local HTTP API, sharing one thread pool
+    Serve(ServeArgs),
+}
+
+#[derive(Args, Debug)]
+struct SearchArgs {
+    /// take options from [profile.<name>] in the config file; options given
+    /// here override the profile's, and --no-<option> drops one of its options,
+    /// e.g. --no-case-sensitive or --no-create2-deployer for a mnemonic search
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// TOML file holding the profiles
+    /// default is vanitygen.toml
+    #[arg(long, requires = "profile")]
+    config: Option<PathBuf>,
+
+    /// print the effective options as a profile and exit
+    #[arg(long)]
+    print_config: bool,
+
     /// number of threads to use
     /// default is 16
     #[arg(long)]
     threads: Option<usize>,
 
     /// number of jobs to dispatch
-    /// default is 10,000
+    /// default is 1,000,000,000
     #[arg(long)]
     jobs: Option<usize>,
 
     /// number of attempts per job
-    /// default is 100
+    /// default is 1,000
     #[arg(long)]
     each: Option<usize>,
+
+    /// TESTING ONLY: derive all entropy from this seed so runs are reproducible.
+    /// Anyone who knows the seed can regenerate every phrase.
+    #[arg(long, hide = true, conflicts_with = "shard")]
+    seed: Option<u64>,
+
+    /// enumerate entropy sequentially within shard <index>/<count> of the keyspace
+    /// instead of sampling it at random; requires --counter-base
+    #[arg(long, requires = "counter_base")]
+    shard: Option<Shard>,
+
+    /// file holding the 64 hex character secret base shared by all shards
+    #[arg(long, requires = "shard")]
+    counter_base: Option<PathBuf>,
+
+    /// mix extra entropy into the OS RNG: 'dice', 'stdin' or 'file:<path>'
+    #[arg(long, conflicts_with_all = ["seed", "shard"])]
+    extra_entropy: Option<EntropySource>,
+
+    #[command(flatten)]
+    target: TargetArgs,
+
+    /// which found addresses to save, e.g. 'zeros:8|prefix:dead&suffix:beef'
+    /// default is zeros:8
+    #[arg(long)]
+    save: Option<SaveRule>,
+
+    /// where to save matching results: 'mnemonics', 'file:<path>', 'jsonl:<path>'
+    /// (one JSON object per result) or 'sqlite[:<path>]' (a results database,
+    /// see --results-key); repeatable
+    /// default is mnemonics
+    #[arg(long)]
+    save_sink: Vec<SinkSpec>,
+
+    /// public key from `results keygen` that sqlite sinks and the leaderboard
+    /// file seal secrets to; only its secret key can read them back
+    #[arg(long, value_parser = parse_public_key)]
+    results_key: Option<k256::PublicKey>,
+
+    /// also save every address spelling a --words word of at least this many letters
+    #[arg(long, requires = "words")]
+    save_words: Option<usize>,
+
+    /// number of best results to keep on the leaderboard
+    /// default is 20
+    #[arg(long)]
+    top: Option<usize>,
+
+    /// progress log format: 'text', or 'json' for one object per line
+    /// default is text
+    #[arg(long)]
+    log_format: Option<LogFormat>,
+
+    /// address to serve Prometheus metrics on at /metrics, e.g. 0.0.0.0:9100
+    #[arg(long)]
+    metrics: Option<String>,
+}
+
+/// What to search for; shared by `search` and `estimate`.
+#[derive(Args, Debug)]
+struct TargetArgs {
+    /// how to rank addresses
+    /// default is less-than
+    #[arg(long, value_enum)]
+    criteria: Option<CriteriaKind>,
+
+    /// search for addresses starting with these hex digits; replaces --criteria
+    #[arg(long, group = "target", conflicts_with = "criteria")]
+    prefix: Option<String>,
+
+    /// search for addresses matching a pattern such as '^dead????beef', '[0-7]$'
+    /// or 'c0ffee' (anywhere); other regex syntax falls back to a slower regex
+    #[arg(long, group = "target", conflicts_with = "criteria")]
+    pattern: Option<String>,
+
+    /// rank addresses by a criteria expression such as
+    /// 'all(prefix:dead, leading-zero-bytes>=2)' or 'lex(zero-bytes, less-than)';
+    /// combinators are all, any, lex and weighted (e.g. 'weighted(3*zero-bytes, 1*prefix:00)')
+    #[arg(long, group = "target", conflicts_with = "criteria")]
+    rank: Option<String>,
+
+    /// search for addresses spelling a word from this wordlist (one per line), with
+    /// leetspeak digits such as 0=o, 1=i/l, 5=s and 7=t counting as letters
+    #[arg(long, group = "target", conflicts_with = "criteria")]
+    words: Option<PathBuf>,
+
+    /// where a --words word has to appear: 'start', 'end' or 'anywhere'
+    /// default is anywhere
+    #[arg(long, requires = "words")]
+    word_position: Option<WordPosition>,
+
+    /// match --prefix, --pattern or --rank against the EIP-55 checksum casing, e.g. 'DeAdBeEf'
+    #[arg(long, requires = "target")]
+    case_sensitive: bool,
+
+    /// require the address to carry exactly these Uniswap v4 hook permissions,
+    /// e.g. 'before-swap,after-swap'; --criteria then ranks the matches
+    #[arg(long)]
+    hook_flags: Option<HookFlags>,
+
+    /// mine CREATE2 salts for contracts deployed by this factory address
+    /// instead of mnemonic phrases; requires --init-code-hash
+    #[arg(long, value_parser = parse_hex_array::<20>, requires = "init_code_hash")]
+    create2_deployer: Option<[u8; 20]>,
+
+    /// keccak256 of the contract's init code, for --create2-deployer
+    #[arg(long, value_parser = parse_hex_array::<32>, requires = "create2_deployer")]
+    init_code_hash: Option<[u8; 32]>,
+}
+
+#[derive(Args, Debug)]
+struct DeriveArgs {
+    /// read a BIP-39 passphrase from the line after the phrase on stdin
+    #[arg(long)]
+    passphrase: bool,
+
+    /// derivation path up to the account level; the address index is appended
+    /// default is m/44'/60'/0'/0
+    #[arg(long)]
+    path: Option<DerivationPath>,
+
+    /// address index, or range of indices such as '0..10' (end excluded)
+    /// default is 0
+    #[arg(long)]
+    index: Option<IndexRange>,
+
+    /// print one JSON object per address instead of labelled lines
+    #[arg(long)]
+    json: bool,
+
+    /// word list the phrases are in
+    /// default is english
+    #[arg(long, value_parser = parse_language)]
+    language: Option<Language>,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// results file of '<address> <phrase>' lines, or '-' for stdin
+    /// default is /var/log/vanitygen/mnemonics.log
+    file: Option<PathBuf>,
+
+    /// derivation path for lines that don't record one
+    /// default is m/44'/60'/0'/0/0
+    #[arg(long)]
+    path: Option<DerivationPath>,
+
+    /// word list the phrases are in
+    /// default is english
+    #[arg(long, value_parser = parse_language)]
+    language: Option<Language>,
+
+    /// factory address, for lines holding a CREATE2 salt instead of a phrase
+    #[arg(long, value_parser = parse_hex_array::<20>, requires = "init_code_hash")]
+    create2_deployer: Option<[u8; 20]>,
+
+    /// keccak256 of the contract's init code, for --create2-deployer
+    #[arg(long, value_parser = parse_hex_array::<32>, requires = "create2_deployer")]
+    init_code_hash: Option<[u8; 32]>,
+}
+
+#[derive(Args, Debug)]
+struct EstimateArgs {
+    /// number of threads the search would use
+    /// default is 16
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// attempts per second per thread; measured on this machine when omitted
+    #[arg(long)]
+    rate: Option<f64>,
+
+    #[command(flatten)]
+    target: TargetArgs,
+}
+
+#[derive(Args, Debug)]
+struct ResultsArgs {
+    /// results database written by 'sqlite' save sinks
+    /// default is /var/log/vanitygen/results.db
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    action: ResultsAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ResultsAction {
+    /// list saved results, without their secrets
+    List(ResultsFilterArgs),
+    /// list search runs with their machine, times and attempts
+    Runs {
+        /// also print each run's options
+        #[arg(long)]
+        config: bool,
+    },
+    /// print saved results as JSON Lines
+    Export {
+        #[command(flatten)]
+        filter: ResultsFilterArgs,
+
+        /// secret key file from `results keygen`, to print secrets instead of
+        /// their sealed form
+        #[arg(long)]
+        secret_key: Option<PathBuf>,
+    },
+    /// open sealed secrets read from stdin, one per line in the last field, as
+    /// in a sealed leaderboard file or the results of a `serve` job
+    Open {
+        /// secret key file from `results keygen`
+        #[arg(long)]
+        secret_key: PathBuf,
+    },
+    /// create the key pair that sqlite sinks seal secrets to
+    Keygen {
+        /// new file to write the secret key to; keep it off the search machines
+        secret_key: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ResultsFilterArgs {
+    /// only results from this run
+    #[arg(long)]
+    run: Option<i64>,
+
+    /// only results whose score (its first part) is at least this
+    #[arg(long)]
+    min_score: Option<u64>,
+
+    /// only results fully matching a pattern such as '^dead' or 'c0ffee'
+    #[arg(long)]
+    pattern: Option<String>,
+}
+
+impl ResultsFilterArgs {
+    fn resolve(&self) -> Filter {
+        Filter {
+            run: self.run,
+            min_score: self.min_score,
+            pattern: self.pattern.as_deref().map(|pattern| {
+                PatternCriteria::new(pattern, false)
+                    .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit())
+            }),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// address to serve the job API on; there is no authentication, so keep it
+    /// local or behind a proxy that adds some
+    /// default is 127.0.0.1:8080
+    #[arg(long)]
+    listen: Option<String>,
+
+    /// number of threads shared by all running jobs
+    /// default is 16
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// number of jobs kept at once, running or with results not yet fetched;
+    /// further jobs are refused until one is fetched or deleted
+    /// default is 16
+    #[arg(long)]
+    max_jobs: Option<usize>,
+
+    /// largest jobs x each a submitted search may ask for
+    /// default is 10,000,000,000
+    #[arg(long)]
+    max_attempts: Option<usize>,
+
+    /// directory of wordlists that 'words:<file>' criteria may name; without
+    /// it, jobs with words criteria are refused
+    #[arg(long)]
+    wordlist_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct BenchArgs {
+    /// number of attempts for the mnemonic derivation benchmark
+    /// default is 200
+    #[arg(long)]
+    attempts: Option<usize>,
+}
+
+fn read_counter_base(path: &Path) -> SecretEntropy {
+    let contents =
+        Zeroizing::new(fs::read_to_string(path).expect("Failed to read counter base file"));
+    let mut base = Zeroizing::new([0u8; 32]);
+    hex::decode_to_slice(contents.trim(), base.as_mut())
+        .expect("Counter base must be exactly 64 hex characters");
+    base
 }
 
 fn main() {
-    let args = Args::parse();
+    let command = Cli::command();
+    let search = command.find_subcommand("search").expect("search exists");
+    let (argv, unset) = take_unset_options(search, std::env::args_os().collect());
+    let matches = Cli::command().get_matches_from(&argv);
+    match Cli::from_arg_matches(&matches)
+        .unwrap_or_else(|e| e.exit())
+        .command
+    {
+        Command::Search(args) => {
+            let matches = matches
+                .subcommand_matches("search")
+                .expect("search has its own matches");
+            let (args, matches, profile) = apply_profile(args, matches, argv, &unset);
+            let search = Cli::command();
+            let search = search.find_subcommand("search").expect("search exists");
+            let profile = profile
+                .as_ref()
+                .map(|(name, keys)| (name.as_str(), &keys[..]));
+            let config = effective_config(search, &matches, profile);
+            if args.print_config {
+                print!("{}", config);
+            } else {
+                run_search(args, config);
+            }
+        }
+        Command::Derive(args) => {
+            derive::run(
+                args.language.unwrap_or_default(),
+                args.passphrase,
+                &args.path.unwrap_or_else(default_account_path),
+                &args.index.unwrap_or_default(),
+                args.json,
+            )
+            .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+        }
+        Command::Verify(args) => run_verify(args),
+        Command::Estimate(args) => run_estimate(args),
+        Command::Bench(args) => bench::run(args.attempts.unwrap_or(200)),
+        Command::Results(args) => run_results(args),
+        Command::Serve(args) => run_serve(args),
+    }
+}
+
+/// Fill in the options `--profile` sets and the command line doesn't, by
+/// parsing the command line again with the profile's options in front.
+///
+/// Returns the merged options with their matches, and the profile's name with
+/// the options taken from it.
+fn apply_profile(
+    args: SearchArgs,
+    matches: &ArgMatches,
+    mut argv: Vec<std::ffi::OsString>,
+    unset: &[String],
+) -> (SearchArgs, ArgMatches, Option<(String, Vec<String>)>) {
+    let Some(name) = &args.profile else {
+        return (args, matches.clone(), None);
+    };
+    let path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+    let command = Cli::command();
+    let search = command.find_subcommand("search").expect("search exists");
+    let (profile_args, keys) = Profile::load(&path, name)
+        .and_then(|profile| profile.args(search, matches, unset))
+        .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+
+    // `search` is always the first argument, as `Cli` has no options of its own
+    argv.splice(2..2, profile_args.into_iter().map(Into::into));
+    let mut all_matches = Cli::command()
+        .try_get_matches_from(argv)
+        .unwrap_or_else(|e| e.exit());
+    let Ok(Cli {
+        command: Command::Search(args),
+    }) = Cli::from_arg_matches(&all_matches)
+    else {
+        unreachable!("the command line parsed as search before");
+    };
+    let (_, matches) = all_matches
+        .remove_subcommand()
+        .expect("search has its own matches");
+    (args, matches, Some((name.clone(), keys)))
+}
+
+/// What a search looks for, resolved from `TargetArgs`.
+struct Target {
+    criteria: CriteriaKind,
+    prefix: Option<PrefixCriteria>,
+    pattern: Option<PatternCriteria>,
+    rank: Option<Box<dyn CriteriaPredicate + Send + Sync>>,
+    words: Option<DictionaryCriteria>,
+    hook_flags: Option<HookFlags>,
+    create2: Option<Create2AddressGenerator>,
+}
+
+impl TargetArgs {
+    fn resolve(&self) -> Target {
+        let prefix = self.prefix.as_deref().map(|pattern| {
+            PrefixCriteria::new(pattern, self.case_sensitive)
+                .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit())
+        });
+        let pattern = self.pattern.as_deref().map(|pattern| {
+            PatternCriteria::new(pattern, self.case_sensitive)
+                .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit())
+        });
+        let rank = self.rank.as_deref().map(|spec| {
+            parse_criteria(spec, self.case_sensitive)
+                .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit())
+        });
+        if let Some(pattern) = &pattern {
+            info!("Searching for pattern '{}'", pattern.source());
+        }
+        let words = self.words.as_deref().map(|path| {
+            let dictionary = Dictionary::load(path)
+                .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+            let position = self.word_position.unwrap_or(WordPosition::Anywhere);
+            info!(
+                "Searching for {} words from {} ({})",
+                dictionary.word_count().to_formatted_string(&Locale::en),
+                path.display(),
+                position
+            );
+            DictionaryCriteria {
+                dictionary: Arc::new(dictionary),
+                position,
+            }
+        });
+        let create2 = match (self.create2_deployer, self.init_code_hash) {
+            (Some(deployer), Some(init_code_hash)) => {
+                info!(
+                    "Mining CREATE2 salts for deployer 0x{}, init code hash 0x{}",
+                    hex::encode(deployer),
+                    hex::encode(init_code_hash)
+                );
+                Some(Create2AddressGenerator {
+                    deployer,
+                    init_code_hash,
+                })
+            }
+            _ => None,
+        };
+        if let Some(flags) = self.hook_flags {
+            info!("Requiring hook permissions {}", flags);
+            if create2.is_none() {
+                warn!(
+                    "--hook-flags without --create2-deployer searches EOAs, which can't be hooks"
+                );
+            }
+        }
+        Target {
+            criteria: self.criteria.unwrap_or(CriteriaKind::LessThan),
+            prefix,
+            pattern,
+            rank,
+            words,
+            hook_flags: self.hook_flags,
+            create2,
+        }
+    }
+}
+
+impl Target {
+    /// The search's criteria as a single trait object, for reporting on it
+    /// outside the monomorphized search.
+    fn boxed_criteria(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        let criteria: Box<dyn CriteriaPredicate + Send + Sync> = if let Some(prefix) = &self.prefix
+        {
+            Box::new(prefix.clone())
+        } else if let Some(pattern) = &self.pattern {
+            Box::new(pattern.clone())
+        } else if let Some(rank) = &self.rank {
+            rank.clone()
+        } else if let Some(words) = &self.words {
+            Box::new(words.clone())
+        } else {
+            match self.criteria {
+                CriteriaKind::LessThan => Box::new(LessThanCriteria),
+                CriteriaKind::LeadingZeroBytes => Box::new(LeadingZeroBytesCriteria),
+                CriteriaKind::ZeroBytes | CriteriaKind::Gas => Box::new(ZeroBytesCriteria),
+                CriteriaKind::RepeatedRun => Box::new(RepeatedRunCriteria),
+                CriteriaKind::RepeatedEnds => Box::new(RepeatedEndsCriteria),
+                CriteriaKind::Palindrome => Box::new(PalindromeCriteria),
+                CriteriaKind::DigitRun => Box::new(DigitRunCriteria),
+                CriteriaKind::LetterRun => Box::new(LetterRunCriteria),
+                CriteriaKind::Mirror => Box::new(MirrorCriteria),
+            }
+        };
+        match self.hook_flags {
+            Some(flags) => Box::new(HookFlagsCriteria {
+                flags,
+                then: criteria,
+            }),
+            None => criteria,
+        }
+    }
+}
+
+fn run_search(args: SearchArgs, config: String) {
     let num_threads: usize = args.threads.unwrap_or(16);
     let num_jobs: usize = args.jobs.unwrap_or(1_000_000_000);
     let attempts_per_job: usize = args.each.unwrap_or(1_000);
 
-    setup_logger().expect("Failed to set up logger");
+    setup_logger(args.log_format.unwrap_or_default()).expect("Failed to set up logger");
+    if let Some(profile) = &args.profile {
+        info!(
+            "Using profile '{}' from {}",
+            profile,
+            args.config
+                .as_deref()
+                .unwrap_or(Path::new(CONFIG_FILE))
+                .display()
+        );
+    }
     info!(
         "Using {} threads, {} jobs, {} attempts per job --> {} total attempts",
         num_threads.to_formatted_string(&Locale::en),
@@ -52,21 +644,436 @@ fn main() {
         (num_jobs * attempts_per_job).to_formatted_string(&Locale::en)
     );
 
-    let rng: Box<dyn NumberGenerator + Send + Sync> = Box::new(RandNumberGenerator {});
-    let address_generator: Box<dyn AddressGenerator + Send + Sync> =
-        Box::new(MnemonicAddressGenerator {
-            language: Language::English,
-        });
-    let criteria: Box<dyn CriteriaPredicate + Send + Sync> = Box::new(LessThanCriteria {});
-    let searcher_pool = ThreadPoolSearcher::new(
+    let target = args.target.resolve();
+    let criteria = target.criteria;
+    let save_rule = args.save.clone().unwrap_or_default();
+    let save_sinks = if args.save_sink.is_empty() {
+        vec![SinkSpec::MnemonicLog]
+    } else {
+        args.save_sink.clone()
+    };
+    if args.results_key.is_none() && save_sinks.iter().any(|s| matches!(s, SinkSpec::Sqlite(_))) {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "sqlite save sinks only store sealed secrets; pass --results-key from `results keygen`",
+            )
+            .exit();
+    }
+    // each job draws one extra number for its initial candidate
+    let stride = attempts_per_job as u64 + 1;
+    if let Some(shard) = args.shard {
+        if !CounterNumberGenerator::fits(shard, stride, num_jobs) {
+            Cli::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "{} jobs of {} attempts overflow shard {}; lower --jobs or --each",
+                        num_jobs, stride, shard
+                    ),
+                )
+                .exit();
+        }
+    }
+    let save_words = args
+        .save_words
+        .and_then(|min_len| Some((target.words.clone()?, min_len)));
+    if let Some((_, min_len)) = &save_words {
+        info!("Saving words of at least {} letters", min_len);
+    }
+    info!(
+        "Saving {} to {}",
+        save_rule,
+        save_sinks
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let progress = Arc::new(SearchProgress::new(num_threads));
+    if let Some(listen) = &args.metrics {
+        metrics::serve(listen, progress.clone())
+            .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+    }
+
+    let run_info = RunInfo::new(config, args.results_key);
+    let settings = SearchSettings {
         num_threads,
         num_jobs,
         attempts_per_job,
+        top: args.top.unwrap_or(20),
+        target,
+        saver: Saver {
+            rule: save_rule,
+            words: save_words,
+            sinks: save_sinks
+                .iter()
+                .map(|s| s.open(&run_info).expect("Failed to open save sink"))
+                .collect(),
+        },
+        progress,
+        results_key: args.results_key,
+    };
+
+    // The CLI picks concrete types here; everything below `search` is monomorphized.
+    let leaderboard = if let Some(seed) = args.seed {
+        warn!("************************************************************");
+        warn!("* --seed {} is set: entropy is DETERMINISTIC.", seed);
+        warn!("* Every phrase from this run can be regenerated from the seed.");
+        warn!("* DO NOT send funds to any address found by this run.");
+        warn!("************************************************************");
+        search(settings, SeededNumberGenerator::new(seed))
+    } else if let (Some(shard), Some(path)) = (args.shard, &args.counter_base) {
+        let counter = CounterNumberGenerator::new(&read_counter_base(path), shard, stride);
+        info!("Enumerating shard {} sequentially", shard);
+        search(settings, counter)
+    } else if let Some(source) = &args.extra_entropy {
+        let secret = source.read().expect("Failed to read extra entropy");
+        info!(
+            "Run metadata: extra entropy from {}, fingerprint {}",
+            source,
+            fingerprint(&secret)
+        );
+        search(
+            settings,
+            MixedNumberGenerator::new(RandNumberGenerator {}, &secret),
+        )
+    } else {
+        search(settings, RandNumberGenerator {})
+    };
+
+    match args.results_key {
+        Some(_) => info!(
+            "Leaderboard (secrets sealed to --results-key in {}, see `results open`):",
+            LEADERBOARD_FILE
+        ),
+        None => info!("Leaderboard (secrets in {}):", LEADERBOARD_FILE),
+    }
+    for (rank, entry) in leaderboard.entries().iter().enumerate() {
+        info!(
+            "  #{:<3} {}     score {}",
+            rank + 1,
+            to_checksum_address(&entry.address),
+            entry.score
+        );
+    }
+    if let Some(nicest) = leaderboard.nicest() {
+        info!("Nicest: {}", to_checksum_address(&nicest.address));
+    }
+    if let Some(best) = leaderboard.best() {
+        info!("Best: {}", to_checksum_address(&best.address));
+        if let CriteriaKind::Gas = criteria {
+            info!(
+                "Saves {} gas per calldata use",
+                calldata_gas_savings(&best.address)
+            );
+        }
+    }
+}
+
+fn run_verify(args: VerifyArgs) {
+    let verifier = Verifier {
+        language: args.language.unwrap_or_default(),
+        path: args
+            .path
+            .unwrap_or_else(|| DEFAULT_PATH.parse().expect("default path is valid")),
+        create2: args.create2_deployer.zip(args.init_code_hash).map(
+            |(deployer, init_code_hash)| Create2AddressGenerator {
+                deployer,
+                init_code_hash,
+            },
+        ),
+    };
+    let file = args.file.unwrap_or_else(|| PathBuf::from(MNEMONICS_FILE));
+    let report = verifier.verify_file(&file).unwrap_or_else(|e| {
+        Cli::command()
+            .error(
+                ErrorKind::Io,
+                format!("failed to read {}: {}", file.display(), e),
+            )
+            .exit()
+    });
+    println!(
+        "{}: {} verified, {} mismatched, {} duplicates, {} malformed",
+        file.display(),
+        report.verified,
+        report.mismatched,
+        report.duplicates,
+        report.malformed
+    );
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
+
+fn run_results(args: ResultsArgs) {
+    let db = args.db.unwrap_or_else(|| PathBuf::from(RESULTS_DB));
+    let result = match args.action {
+        ResultsAction::List(filter) => results::list(&db, &filter.resolve()),
+        ResultsAction::Runs { config } => results::runs(&db, config),
+        ResultsAction::Export { filter, secret_key } => secret_key
+            .as_deref()
+            .map(read_secret_key)
+            .transpose()
+            .and_then(|secret_key| results::export(&db, &filter.resolve(), secret_key.as_ref())),
+        ResultsAction::Open { secret_key } => {
+            read_secret_key(&secret_key).and_then(|secret_key| results::open_lines(&secret_key))
+        }
+        ResultsAction::Keygen { secret_key } => write_secret_key(&secret_key).map(|public_key| {
+            println!("Secret key written to {}", secret_key.display());
+            println!(
+                "Search with --results-key {}",
+                format_public_key(&public_key)
+            );
+        }),
+    };
+    result.unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+}
+
+fn run_serve(args: ServeArgs) {
+    // serve never writes mnemonics or coverage, so it can run without the log directory
+    if let Err(e) = setup_logger(LogFormat::Text) {
+        eprintln!(
+            "Can't write logs to /var/log/vanitygen ({}); logging to the console only",
+            e
+        );
+        setup_console_logger(LogFormat::Text).expect("Failed to set up logger");
+    }
+    let thread_pool = ThreadPoolBuilder::new()
+        .num_threads(args.threads.unwrap_or(16))
+        .build()
+        .expect("Failed to build thread pool");
+    let listen = args.listen.as_deref().unwrap_or("127.0.0.1:8080");
+    let limits = serve::Limits {
+        max_jobs: args.max_jobs.unwrap_or(16),
+        max_attempts: args.max_attempts.unwrap_or(10_000_000_000),
+        wordlist_dir: args.wordlist_dir,
+    };
+    serve::run(listen, thread_pool, limits)
+        .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+}
+
+fn run_estimate(args: EstimateArgs) {
+    let num_threads = args.threads.unwrap_or(16);
+    let target = args.target.resolve();
+    let criteria = target.boxed_criteria();
+    let Some(attempts) = criteria.difficulty() else {
+        if criteria.done().is_some() {
+            println!("The odds of a full match for these criteria aren't known, so there");
+            println!("is nothing to estimate.");
+        } else {
+            println!("These criteria have no full match to estimate; give them a goal,");
+            println!("e.g. --prefix, --pattern or '>=<n>' in --rank.");
+        }
+        return;
+    };
+    let rate = args.rate.unwrap_or_else(|| match target.create2 {
+        Some(create2) => measure_rate(&create2),
+        None => measure_rate(&MnemonicAddressGenerator {
+            language: Language::English,
+        }),
+    }) * num_threads as f64;
+
+    println!("Expected attempts : {}", format_attempts(attempts));
+    println!(
+        "Rate              : {} attempts/sec on {} threads",
+        format_attempts(rate),
+        num_threads
+    );
+    // the number of attempts until the first full match is geometric
+    for chance in [0.5f64, 0.9, 0.99] {
+        let needed = attempts * -(1.0 - chance).ln();
+        println!(
+            "{:>3}% chance       : {} attempts, {}",
+            (chance * 100.0) as u32,
+            format_attempts(needed),
+            format_duration(needed / rate)
+        );
+    }
+}
+
+/// Single-threaded attempts per second of the address generator, measured for
+/// about two seconds.
+fn measure_rate<A: AddressGenerator>(address_generator: &A) -> f64 {
+    let mut rng = RandNumberGenerator {};
+    let start = Instant::now();
+    let mut attempts = 0u64;
+    while start.elapsed() < Duration::from_secs(2) {
+        let entropy = rng.generate();
+        std::hint::black_box(address_generator.generate(&entropy).ok());
+        attempts += 1;
+    }
+    attempts as f64 / start.elapsed().as_secs_f64()
+}
+
+fn format_duration(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("years", 365.25 * 86_400.0),
+        ("days", 86_400.0),
+        ("hours", 3_600.0),
+        ("minutes", 60.0),
+        ("seconds", 1.0),
+    ];
+    let (unit, size) = UNITS
+        .iter()
+        .find(|(_, size)| seconds >= *size)
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+    format!("{} {}", format_attempts(seconds / size), unit)
+}
+
+/// Options shared by every search, whichever generator the CLI picked.
+struct SearchSettings {
+    num_threads: usize,
+    num_jobs: usize,
+    attempts_per_job: usize,
+    top: usize,
+    target: Target,
+    saver: Saver,
+    progress: Arc<SearchProgress>,
+    /// Key the leaderboard file's secrets are sealed to, if any.
+    results_key: Option<k256::PublicKey>,
+}
+
+/// Which criteria ranks the leaderboard.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum CriteriaKind {
+    /// lowest address in hex order
+    LessThan,
+    /// most leading zero bytes, then most zero bytes
+    LeadingZeroBytes,
+    /// most zero bytes anywhere, then most leading zero bytes
+    ZeroBytes,
+    /// most calldata gas saved (same ranking as zero-bytes, reported in gas)
+    Gas,
+    /// longest run of one repeated digit
+    RepeatedRun,
+    /// longest runs of the same digit at both ends
+    RepeatedEnds,
+    /// longest palindromic stretch of digits
+    Palindrome,
+    /// longest stretch of decimal digits
+    DigitRun,
+    /// longest stretch of the letters a-f
+    LetterRun,
+    /// most digits at the end mirroring the start
+    Mirror,
+}
+
+fn search<N>(settings: SearchSettings, rng: N) -> Leaderboard
+where
+    N: NumberGenerator + Clone + Send + Sync,
+{
+    if let Some(prefix) = settings.target.prefix.clone() {
+        return search_hooks(settings, rng, prefix);
+    }
+    if let Some(pattern) = settings.target.pattern.clone() {
+        return search_hooks(settings, rng, pattern);
+    }
+    if let Some(rank) = settings.target.rank.clone() {
+        return search_hooks(settings, rng, rank);
+    }
+    if let Some(words) = settings.target.words.clone() {
+        return search_hooks(settings, rng, words);
+    }
+    match settings.target.criteria {
+        CriteriaKind::LessThan => search_hooks(settings, rng, LessThanCriteria),
+        CriteriaKind::LeadingZeroBytes => search_hooks(settings, rng, LeadingZeroBytesCriteria),
+        CriteriaKind::ZeroBytes | CriteriaKind::Gas => {
+            search_hooks(settings, rng, ZeroBytesCriteria)
+        }
+        CriteriaKind::RepeatedRun => search_hooks(settings, rng, RepeatedRunCriteria),
+        CriteriaKind::RepeatedEnds => search_hooks(settings, rng, RepeatedEndsCriteria),
+        CriteriaKind::Palindrome => search_hooks(settings, rng, PalindromeCriteria),
+        CriteriaKind::DigitRun => search_hooks(settings, rng, DigitRunCriteria),
+        CriteriaKind::LetterRun => search_hooks(settings, rng, LetterRunCriteria),
+        CriteriaKind::Mirror => search_hooks(settings, rng, MirrorCriteria),
+    }
+}
+
+fn search_hooks<N, C>(settings: SearchSettings, rng: N, criteria: C) -> Leaderboard
+where
+    N: NumberGenerator + Clone + Send + Sync,
+    C: CriteriaPredicate + Clone + Send + Sync + 'static,
+{
+    match settings.target.hook_flags {
+        Some(flags) => search_addresses(
+            settings,
+            rng,
+            HookFlagsCriteria {
+                flags,
+                then: criteria,
+            },
+        ),
+        None => search_addresses(settings, rng, criteria),
+    }
+}
+
+fn search_addresses<N, C>(settings: SearchSettings, rng: N, criteria: C) -> Leaderboard
+where
+    N: NumberGenerator + Clone + Send + Sync,
+    C: CriteriaPredicate + Clone + Send + Sync,
+{
+    match settings.target.create2 {
+        Some(create2) => search_with(settings, rng, create2, criteria),
+        None => {
+            let mnemonic = MnemonicAddressGenerator {
+                language: Language::English,
+            };
+            search_with(settings, rng, mnemonic, criteria)
+        }
+    }
+}
+
+fn search_with<N, A, C>(
+    settings: SearchSettings,
+    rng: N,
+    address_generator: A,
+    criteria: C,
+) -> Leaderboard
+where
+    N: NumberGenerator + Clone + Send + Sync,
+    A: AddressGenerator + Clone + Send + Sync,
+    C: CriteriaPredicate + Clone + Send + Sync,
+{
+    if let Some(attempts) = criteria.difficulty() {
+        info!(
+            "Expected attempts for a full match: {}",
+            format_attempts(attempts)
+        );
+    }
+    let thread_pool = ThreadPoolBuilder::new()
+        .num_threads(settings.num_threads)
+        .build()
+        .expect("Failed to create thread pool");
+    let searcher_pool = ThreadPoolSearcher::new(
+        Arc::new(thread_pool),
+        settings.num_jobs,
+        settings.attempts_per_job,
+        settings.top,
         rng,
         address_generator,
         criteria,
-    );
-    let best_address = searcher_pool.run();
+    )
+    .with_saver(settings.saver)
+    .with_progress(settings.progress);
+    match settings.results_key {
+        Some(key) => searcher_pool.with_sealed_leaderboard(key).run(),
+        None => searcher_pool.run(),
+    }
+}
+
+fn format_attempts(attempts: f64) -> String {
+    if attempts < u64::MAX as f64 {
+        (attempts.round() as u64).to_formatted_string(&Locale::en)
+    } else {
+        format!("{:.3e}", attempts)
+    }
+}
 
-    info!("Best: {}", best_address);
+fn parse_hex_array<const LEN: usize>(s: &str) -> Result<[u8; LEN], String> {
+    let mut bytes = [0u8; LEN];
+    hex::decode_to_slice(s.trim_start_matches("0x"), &mut bytes)
+        .map_err(|_| format!("expected {} hex characters, got '{}'", LEN * 2, s))?;
+    Ok(bytes)
 }
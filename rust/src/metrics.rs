@@ -0,0 +1,159 @@
+use std::{fmt::Write, sync::Arc, thread, time::Instant};
+
+use log::{info, warn};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::search::SearchProgress;
+
+/// A search to report, with the `job` label it gets in `serve` mode.
+pub type Source<'a> = (Option<u64>, &'a SearchProgress);
+
+fn labels(job: Option<u64>, thread: Option<usize>) -> String {
+    let labels: Vec<String> = job
+        .map(|job| format!("job=\"{}\"", job))
+        .into_iter()
+        .chain(thread.map(|thread| format!("thread=\"{}\"", thread)))
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+/// Append one metric, with its `vanitygen_` prefix and a sample per label set.
+pub fn metric(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, f64)>,
+) {
+    let _ = writeln!(out, "# HELP vanitygen_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE vanitygen_{} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "vanitygen_{}{} {}", name, labels, value);
+    }
+}
+
+/// Average rate since the search started.
+fn per_second(attempts: usize, progress: &SearchProgress) -> f64 {
+    match progress.elapsed().map(|elapsed| elapsed.as_secs_f64()) {
+        Some(seconds) if seconds > 0.0 => attempts as f64 / seconds,
+        _ => 0.0,
+    }
+}
+
+/// The searches' counters in the Prometheus text format.
+pub fn render(sources: &[Source], started: Instant) -> String {
+    let mut out = String::new();
+    let per_search = |value: fn(&SearchProgress) -> f64| {
+        sources
+            .iter()
+            .map(move |(job, progress)| (labels(*job, None), value(progress)))
+    };
+    let per_thread = |value: fn(usize, &SearchProgress) -> f64| {
+        sources.iter().flat_map(move |(job, progress)| {
+            progress
+                .thread_attempts()
+                .into_iter()
+                .enumerate()
+                .map(move |(thread, attempts)| {
+                    (labels(*job, Some(thread)), value(attempts, progress))
+                })
+        })
+    };
+
+    metric(
+        &mut out,
+        "attempts_total",
+        "counter",
+        "Addresses generated and scored.",
+        per_search(|progress| progress.attempts() as f64),
+    );
+    metric(
+        &mut out,
+        "attempts_per_second",
+        "gauge",
+        "Average attempts per second since the search started.",
+        per_search(|progress| per_second(progress.attempts(), progress)),
+    );
+    metric(
+        &mut out,
+        "thread_attempts_total",
+        "counter",
+        "Addresses generated and scored, by thread pool index.",
+        per_thread(|attempts, _| attempts as f64),
+    );
+    metric(
+        &mut out,
+        "thread_attempts_per_second",
+        "gauge",
+        "Average attempts per second since the search started, by thread pool index.",
+        per_thread(per_second),
+    );
+    metric(
+        &mut out,
+        "best_score",
+        "gauge",
+        "Headline score of the best result so far, e.g. its leading zero bytes.",
+        per_search(|progress| progress.best().map_or(0, |(_, score)| score.headline()) as f64),
+    );
+    metric(
+        &mut out,
+        "saves_total",
+        "counter",
+        "Results written to the save sinks.",
+        per_search(|progress| progress.saves() as f64),
+    );
+    metric(
+        &mut out,
+        "remaining_jobs",
+        "gauge",
+        "Jobs of the search not yet started.",
+        per_search(|progress| progress.remaining_jobs() as f64),
+    );
+    metric(
+        &mut out,
+        "uptime_seconds",
+        "gauge",
+        "Seconds since the metrics endpoint started.",
+        [(String::new(), started.elapsed().as_secs_f64())],
+    );
+    out
+}
+
+/// Send `render`ed metrics, or a 404 for any other request.
+pub fn respond(request: Request, metrics: Option<String>) {
+    let (status, body) = match metrics {
+        Some(metrics) => (200, metrics),
+        None => (404, "not found\n".to_string()),
+    };
+    let header = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+        .expect("a static header is valid");
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        warn!("Failed to send metrics: {}", e);
+    }
+}
+
+/// Serve `GET /metrics` for `progress` on `listen`, from a background thread.
+pub fn serve(listen: &str, progress: Arc<SearchProgress>) -> Result<(), String> {
+    let server =
+        Server::http(listen).map_err(|e| format!("failed to listen on {}: {}", listen, e))?;
+    info!("Serving metrics on http://{}/metrics", listen);
+    let started = Instant::now();
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for request in server.incoming_requests() {
+                let metrics = (*request.method() == Method::Get && request.url() == "/metrics")
+                    .then(|| render(&[(None, &progress)], started));
+                respond(request, metrics);
+            }
+        })
+        .map_err(|e| format!("failed to start the metrics server: {}", e))?;
+    Ok(())
+}
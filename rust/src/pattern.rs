@@ -0,0 +1,458 @@
+use log::warn;
+use regex::Regex;
+
+use crate::{
+    criteria::{CriteriaPredicate, Score},
+    crypto::to_checksum_address,
+};
+
+/// Hex digits in an address.
+const NIBBLES: usize = 40;
+
+/// An address as 160 bits, big-endian, packed into three words (the last half full).
+type Words = [u64; 3];
+
+/// Match addresses against a restricted pattern language over the 40 hex digits:
+///
+/// * `0`-`9`, `a`-`f` - a fixed digit
+/// * `?` - any digit
+/// * `[0-7]`, `[02468ace]` - a class of digits
+/// * `^` / `$` - anchor the pattern to the start / end of the address
+///
+/// Patterns compile once into nibble mask/value pairs, one per position the
+/// pattern can sit at, so checking an address is a few ANDs and compares.
+/// Classes that aren't a mask/value pair (such as `[0-9]`) are checked digit by
+/// digit against their set of allowed digits instead. Anything using other
+/// regex syntax falls back to a regex on the lowercase hex string.
+#[derive(Clone)]
+pub struct PatternCriteria {
+    source: String,
+    matcher: Matcher,
+}
+
+#[derive(Clone)]
+enum Matcher {
+    Masks {
+        /// (mask, value) for every offset the pattern can start at
+        offsets: Vec<(Words, Words)>,
+        /// allowed digits of classes that aren't a mask/value pair, as
+        /// (position in pattern, one bit per digit)
+        classes: Vec<(usize, u16)>,
+        /// required checksum case of fixed letters, as (position in pattern, uppercase)
+        cases: Vec<(usize, bool)>,
+        /// number of digits the pattern constrains
+        constrained: usize,
+        /// probability that one offset matches, for the difficulty estimate
+        odds: f64,
+    },
+    Regex(Regex),
+}
+
+/// One position of a compiled pattern: which of the 16 digits it accepts.
+#[derive(Clone, Copy)]
+struct Element {
+    digits: u16,
+    /// the literal character, if it was a single fixed digit
+    literal: Option<char>,
+}
+
+impl PatternCriteria {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Self, String> {
+        let matcher = match parse(pattern)?.map(|parsed| compile(&parsed, case_sensitive)) {
+            Some(matcher) => matcher,
+            None if case_sensitive => {
+                return Err(format!(
+                    "pattern '{}' needs a regex, which can't check checksum case",
+                    pattern
+                ))
+            }
+            None => {
+                let regex = fallback_regex(pattern)?;
+                warn!(
+                    "Pattern '{}' can't be compiled to nibble masks; falling back to the slower regex /{}/",
+                    pattern,
+                    regex.as_str()
+                );
+                Matcher::Regex(regex)
+            }
+        };
+        Ok(Self {
+            source: pattern.to_string(),
+            matcher,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Number of constrained digits matched at the best offset; a full match
+    /// equals `constrained`, and a regex scores 1 for a match and 0 otherwise.
+    pub fn matched(&self, address: &str) -> usize {
+        let hex = address.trim_start_matches("0x");
+        match &self.matcher {
+            Matcher::Regex(regex) => regex.is_match(&hex.to_ascii_lowercase()) as usize,
+            Matcher::Masks {
+                offsets,
+                classes,
+                cases,
+                constrained,
+                ..
+            } => {
+                let Some(words) = to_words(hex) else {
+                    return 0;
+                };
+                let mut best = 0;
+                for (offset, (mask, value)) in offsets.iter().enumerate() {
+                    let mismatched = mismatched_nibbles(&words, mask, value)
+                        + classes
+                            .iter()
+                            .filter(|(i, digits)| digits & 1 << nibble(&words, offset + i) == 0)
+                            .count();
+                    let mut matched = constrained - mismatched;
+                    if mismatched == 0
+                        && !cases.is_empty()
+                        && !Self::case_matches(hex, offset, cases)
+                    {
+                        matched -= 1;
+                    }
+                    if matched == *constrained {
+                        return matched;
+                    }
+                    best = best.max(matched);
+                }
+                best
+            }
+        }
+    }
+
+    fn case_matches(hex: &str, offset: usize, cases: &[(usize, bool)]) -> bool {
+        let checksummed = to_checksum_address(hex);
+        let checksummed = checksummed.as_bytes();
+        cases
+            .iter()
+            .all(|(i, upper)| checksummed[2 + offset + i].is_ascii_uppercase() == *upper)
+    }
+}
+
+impl CriteriaPredicate for PatternCriteria {
+    fn score(&self, address: &str) -> Score {
+        Score::new([self.matched(address) as u64])
+    }
+
+    fn done(&self) -> Option<Score> {
+        let full = match &self.matcher {
+            Matcher::Masks { constrained, .. } => *constrained,
+            Matcher::Regex(_) => 1,
+        };
+        Some(Score::new([full as u64]))
+    }
+
+    fn difficulty(&self) -> Option<f64> {
+        match &self.matcher {
+            Matcher::Masks { offsets, odds, .. } => Some(1.0 / (odds * offsets.len() as f64)),
+            Matcher::Regex(_) => None,
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn CriteriaPredicate + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+struct Parsed {
+    elements: Vec<Element>,
+    start: bool,
+    end: bool,
+}
+
+/// Parse the restricted language, or `None` if the pattern uses other regex syntax.
+fn parse(pattern: &str) -> Result<Option<Parsed>, String> {
+    let pattern = pattern.strip_prefix("0x").unwrap_or(pattern);
+    let (start, pattern) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (end, pattern) = match pattern.strip_suffix('$') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+
+    let mut elements = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let element = match c {
+            '?' => Element {
+                digits: u16::MAX,
+                literal: None,
+            },
+            '[' => {
+                let mut digits = 0u16;
+                let mut class: Vec<char> = Vec::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    class.push(c);
+                }
+                let mut i = 0;
+                while i < class.len() {
+                    let Some(lo) = digit(class[i]) else {
+                        return Ok(None);
+                    };
+                    if class.get(i + 1) == Some(&'-') && i + 2 < class.len() {
+                        let Some(hi) = digit(class[i + 2]) else {
+                            return Ok(None);
+                        };
+                        for d in lo.min(hi)..=lo.max(hi) {
+                            digits |= 1 << d;
+                        }
+                        i += 3;
+                    } else {
+                        digits |= 1 << lo;
+                        i += 1;
+                    }
+                }
+                if digits == 0 {
+                    return Err(format!("empty class in pattern '{}'", pattern));
+                }
+                Element {
+                    digits,
+                    literal: None,
+                }
+            }
+            _ => match digit(c) {
+                Some(d) => Element {
+                    digits: 1 << d,
+                    literal: Some(c),
+                },
+                None => return Ok(None),
+            },
+        };
+        elements.push(element);
+    }
+
+    if elements.is_empty()
+        || elements.len() > NIBBLES
+        || (start && end && elements.len() != NIBBLES)
+    {
+        return Err(format!(
+            "pattern '{}' must cover 1 to {} digits, and all {} when anchored at both ends",
+            pattern, NIBBLES, NIBBLES
+        ));
+    }
+    Ok(Some(Parsed {
+        elements,
+        start,
+        end,
+    }))
+}
+
+/// Compile to nibble masks, plus digit sets for classes that have no mask.
+fn compile(parsed: &Parsed, case_sensitive: bool) -> Matcher {
+    let mut nibbles: Vec<(u64, u64)> = Vec::with_capacity(parsed.elements.len());
+    let mut classes = Vec::new();
+    let mut odds = 1.0;
+    let mut cases = Vec::new();
+    for (i, element) in parsed.elements.iter().enumerate() {
+        match mask_value(element.digits) {
+            Some(pair) => nibbles.push(pair),
+            None => {
+                nibbles.push((0, 0));
+                classes.push((i, element.digits));
+            }
+        }
+        odds *= element.digits.count_ones() as f64 / 16.0;
+        if let Some(c) = element
+            .literal
+            .filter(|c| case_sensitive && c.is_ascii_alphabetic())
+        {
+            cases.push((i, c.is_ascii_uppercase()));
+            odds /= 2.0;
+        }
+    }
+
+    let len = parsed.elements.len();
+    let starts: Vec<usize> = match (parsed.start, parsed.end) {
+        (true, _) => vec![0],
+        (false, true) => vec![NIBBLES - len],
+        (false, false) => (0..=NIBBLES - len).collect(),
+    };
+    let offsets = starts
+        .into_iter()
+        .map(|start| {
+            let mut mask: Words = [0; 3];
+            let mut value: Words = [0; 3];
+            for (i, (m, v)) in nibbles.iter().enumerate() {
+                let (word, shift) = nibble_position(start + i);
+                mask[word] |= m << shift;
+                value[word] |= v << shift;
+            }
+            (mask, value)
+        })
+        .collect();
+
+    Matcher::Masks {
+        offsets,
+        classes,
+        cases,
+        constrained: parsed
+            .elements
+            .iter()
+            .filter(|element| element.digits != u16::MAX)
+            .count(),
+        odds,
+    }
+}
+
+/// The regex equivalent of a pattern: `?` becomes `.` only in restricted-language
+/// patterns, which are the ones made solely of digits, `?`, classes and anchors.
+fn fallback_regex(pattern: &str) -> Result<Regex, String> {
+    let pattern = pattern.strip_prefix("0x").unwrap_or(pattern);
+    let restricted = pattern
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() || "?[]-^$".contains(c));
+    let regex = if restricted {
+        pattern.replace('?', ".").to_ascii_lowercase()
+    } else {
+        pattern.to_string()
+    };
+    Regex::new(&regex).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))
+}
+
+fn digit(c: char) -> Option<u32> {
+    c.to_digit(16)
+}
+
+/// The (mask, value) nibble pair accepting exactly `digits`, if one exists.
+fn mask_value(digits: u16) -> Option<(u64, u64)> {
+    let members: Vec<u64> = (0..16).filter(|d| digits & (1 << d) != 0).collect();
+    let first = *members.first()?;
+    // bits on which every member agrees
+    let mask = members.iter().fold(0xf, |mask, d| mask & !(d ^ first));
+    let value = first & mask;
+    (members.len() == 1 << (4 - mask.count_ones())).then_some((mask, value))
+}
+
+fn nibble_position(i: usize) -> (usize, u64) {
+    (i / 16, (15 - (i % 16)) as u64 * 4)
+}
+
+/// The digit at position `i` of the address.
+fn nibble(words: &Words, i: usize) -> u64 {
+    let (word, shift) = nibble_position(i);
+    words[word] >> shift & 0xf
+}
+
+fn to_words(hex: &str) -> Option<Words> {
+    if hex.len() != NIBBLES {
+        return None;
+    }
+    let mut words: Words = [0; 3];
+    for (i, chunk) in hex.as_bytes().chunks(16).enumerate() {
+        let chunk = std::str::from_utf8(chunk).ok()?;
+        words[i] = u64::from_str_radix(chunk, 16).ok()? << ((16 - chunk.len()) * 4);
+    }
+    Some(words)
+}
+
+/// Number of masked nibbles where `words` differs from `value`.
+fn mismatched_nibbles(words: &Words, mask: &Words, value: &Words) -> usize {
+    const LOW_BITS: u64 = 0x1111_1111_1111_1111;
+    words
+        .iter()
+        .zip(mask.iter().zip(value.iter()))
+        .map(|(w, (m, v))| {
+            let x = (w ^ v) & m;
+            ((x | x >> 1 | x >> 2 | x >> 3) & LOW_BITS).count_ones() as usize
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An address of `fill` digits with `digits` written in at `start`.
+    fn address(fill: char, start: usize, digits: &str) -> String {
+        let mut hex: Vec<char> = std::iter::repeat_n(fill, NIBBLES).collect();
+        for (i, c) in digits.chars().enumerate() {
+            hex[start + i] = c;
+        }
+        format!("0x{}", hex.into_iter().collect::<String>())
+    }
+
+    #[test]
+    fn nibbles_are_packed_big_endian_sixteen_to_a_word() {
+        assert_eq!(nibble_position(0), (0, 60));
+        assert_eq!(nibble_position(15), (0, 0));
+        assert_eq!(nibble_position(16), (1, 60));
+        assert_eq!(nibble_position(39), (2, 32));
+
+        let hex = "0123456789abcdef0123456789abcdeffedcba98";
+        let words = to_words(hex).unwrap();
+        assert_eq!(
+            words,
+            [
+                0x0123_4567_89ab_cdef,
+                0x0123_4567_89ab_cdef,
+                0xfedc_ba98_0000_0000
+            ]
+        );
+        for (i, c) in hex.chars().enumerate() {
+            assert_eq!(nibble(&words, i), c.to_digit(16).unwrap() as u64);
+        }
+        assert!(to_words(&hex[1..]).is_none());
+        assert!(to_words(&hex.replace('0', "g")).is_none());
+    }
+
+    #[test]
+    fn mismatched_nibbles_counts_only_masked_digits() {
+        let words = to_words("00000000000000000000000000000000000000ff").unwrap();
+        let value = [0; 3];
+        assert_eq!(mismatched_nibbles(&words, &[u64::MAX; 3], &value), 2);
+        // a single differing bit makes its whole digit mismatch
+        assert_eq!(
+            mismatched_nibbles(&words, &[0, 0, 0x0000_0001_0000_0000], &value),
+            1
+        );
+        assert_eq!(
+            mismatched_nibbles(&words, &[u64::MAX, u64::MAX, 0], &value),
+            0
+        );
+    }
+
+    #[test]
+    fn mask_value_exists_only_for_aligned_classes() {
+        assert_eq!(mask_value(1 << 0xa), Some((0xf, 0xa)));
+        assert_eq!(mask_value(0x00ff), Some((0x8, 0x0)));
+        // even digits
+        assert_eq!(mask_value(0x5555), Some((0x1, 0x0)));
+        assert_eq!(mask_value(u16::MAX), Some((0, 0)));
+        // 0-9 and 1-3 aren't a power-of-two sized, bit-aligned set
+        assert_eq!(mask_value(0x03ff), None);
+        assert_eq!(mask_value(0x000e), None);
+        assert_eq!(mask_value(0), None);
+    }
+
+    #[test]
+    fn decimal_classes_compile_to_digit_sets() {
+        let pattern = PatternCriteria::new("^[0-9]ab", false).unwrap();
+        assert!(matches!(&pattern.matcher, Matcher::Masks { classes, .. } if classes.len() == 1));
+        assert_eq!(pattern.done(), Some(Score::new([3])));
+        assert_eq!(pattern.matched(&address('0', 0, "7ab")), 3);
+        assert_eq!(pattern.matched(&address('0', 0, "cab")), 2);
+        assert_eq!(pattern.matched(&address('0', 0, "c00")), 0);
+        let odds = 10.0 / 16.0 / 256.0;
+        assert_eq!(pattern.difficulty(), Some(1.0 / odds));
+    }
+
+    #[test]
+    fn unanchored_classes_are_checked_at_every_offset() {
+        let pattern = PatternCriteria::new("[1-3]ff", false).unwrap();
+        assert_eq!(pattern.matched(&address('0', 20, "2ff")), 3);
+        assert_eq!(pattern.matched(&address('0', 37, "3ff")), 3);
+        assert_eq!(pattern.matched(&address('0', 20, "4ff")), 2);
+        assert!(PatternCriteria::new("[0-9]AB", true).is_ok());
+    }
+}
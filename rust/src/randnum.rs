@@ -1,25 +1,380 @@
-use rand_core::RngCore;
+use std::{fmt, ops::Range, str::FromStr};
+
+use hkdf::Hkdf;
+use num_bigint::BigUint;
+use num_traits::One;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Source entropy for a BIP39 mnemonic phrase
 pub type Entropy = [u8; 32];
 
+/// Entropy that is wiped when dropped; every candidate passes through one of these.
+pub type SecretEntropy = Zeroizing<Entropy>;
+
 pub trait NumberGenerator {
-    fn generate(&mut self) -> Entropy;
+    fn generate(&mut self) -> SecretEntropy;
+
+    /// Position the generator at the start of the sub-stream for the given job.
+    ///
+    /// Called on a fresh clone before every job is searched, so generators can
+    /// hand out per-job output that doesn't depend on thread scheduling.
+    fn start_job(&mut self, _job: usize) {}
+
+    /// The entropy covered by a contiguous run of jobs, as `[start, end)`.
+    ///
+    /// Only generators that enumerate the keyspace can answer this; random
+    /// generators return `None` and no coverage is recorded for them.
+    fn job_range(&self, _jobs: Range<usize>) -> Option<(Entropy, Entropy)> {
+        None
+    }
+
+    /// Whether every job's numbers are fixed in advance, as with a seed.
+    ///
+    /// Searches then hand job results to the leaderboard in job order, so that
+    /// what they find doesn't depend on thread scheduling.
+    fn is_reproducible(&self) -> bool {
+        false
+    }
+
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync>;
+}
+
+impl NumberGenerator for Box<dyn NumberGenerator + Send + Sync> {
+    fn generate(&mut self) -> SecretEntropy {
+        (**self).generate()
+    }
+
+    fn start_job(&mut self, job: usize) {
+        (**self).start_job(job)
+    }
 
-    fn clone_box(&self) -> Box<dyn NumberGenerator>;
+    fn job_range(&self, jobs: Range<usize>) -> Option<(Entropy, Entropy)> {
+        (**self).job_range(jobs)
+    }
+
+    fn is_reproducible(&self) -> bool {
+        (**self).is_reproducible()
+    }
+
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync> {
+        (**self).clone_box()
+    }
+}
+
+impl Clone for Box<dyn NumberGenerator + Send + Sync> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct RandNumberGenerator {}
 
 impl NumberGenerator for RandNumberGenerator {
-    fn generate(&mut self) -> Entropy {
-        let mut entropy = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut entropy);
+    fn generate(&mut self) -> SecretEntropy {
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        rand::thread_rng().fill_bytes(entropy.as_mut());
         entropy
     }
 
-    fn clone_box(&self) -> Box<dyn NumberGenerator> {
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync> {
         Box::new(*self)
     }
 }
+
+/// Deterministic ChaCha20 stream keyed by a fixed seed.
+///
+/// Each job reads from its own ChaCha20 stream (stream id = job index), so a run
+/// with the same seed and job layout always tests the same entropy.
+///
+/// NEVER use this for keys that will hold funds: anyone who knows the seed can
+/// regenerate every phrase.
+#[derive(Clone)]
+pub struct SeededNumberGenerator {
+    rng: ChaCha20Rng,
+}
+
+impl SeededNumberGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl NumberGenerator for SeededNumberGenerator {
+    fn generate(&mut self) -> SecretEntropy {
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        self.rng.fill_bytes(entropy.as_mut());
+        entropy
+    }
+
+    fn start_job(&mut self, job: usize) {
+        self.rng.set_stream(job as u64);
+        self.rng.set_word_pos(0);
+    }
+
+    fn is_reproducible(&self) -> bool {
+        true
+    }
+
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Mixes a user-supplied secret into another generator's output.
+///
+/// The secret and a fresh OS-random salt are combined once per run with
+/// HKDF-SHA256. Each job expands its own ChaCha20 key from that, and every
+/// number is the inner generator's output XORed with the job's stream, so the
+/// result is unpredictable as long as either the OS RNG or the secret is.
+#[derive(Clone)]
+pub struct MixedNumberGenerator<N> {
+    inner: N,
+    hkdf: Hkdf<Sha256>,
+    stream: ChaCha20Rng,
+}
+
+impl<N> MixedNumberGenerator<N> {
+    pub fn new(inner: N, secret: &[u8]) -> Self {
+        let mut salt = Zeroizing::new([0u8; 32]);
+        rand::rngs::OsRng.fill_bytes(salt.as_mut());
+        let hkdf = Hkdf::<Sha256>::new(Some(salt.as_ref()), secret);
+        let stream = Self::job_stream(&hkdf, 0);
+        Self {
+            inner,
+            hkdf,
+            stream,
+        }
+    }
+
+    fn job_stream(hkdf: &Hkdf<Sha256>, job: usize) -> ChaCha20Rng {
+        let mut info = *b"vanitygen job \0\0\0\0\0\0\0\0";
+        info[14..].copy_from_slice(&(job as u64).to_be_bytes());
+        let mut key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(&info, key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        ChaCha20Rng::from_seed(*key)
+    }
+}
+
+impl<N> NumberGenerator for MixedNumberGenerator<N>
+where
+    N: NumberGenerator + Clone + Send + Sync + 'static,
+{
+    fn generate(&mut self) -> SecretEntropy {
+        let mut entropy = self.inner.generate();
+        let mut mask = Zeroizing::new([0u8; 32]);
+        self.stream.fill_bytes(mask.as_mut());
+        for (e, m) in entropy.iter_mut().zip(mask.iter()) {
+            *e ^= m;
+        }
+        entropy
+    }
+
+    fn start_job(&mut self, job: usize) {
+        self.inner.start_job(job);
+        self.stream = Self::job_stream(&self.hkdf, job);
+    }
+
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// One slice of the entropy keyspace, written `index/count` on the command line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u64,
+    pub count: u64,
+}
+
+impl Shard {
+    /// Offset of this shard's first entropy value: `index * floor(2^256 / count)`.
+    fn offset(&self) -> BigUint {
+        self.width() * self.index
+    }
+
+    /// Number of entropy values in each shard.
+    fn width(&self) -> BigUint {
+        (BigUint::one() << 256) / self.count
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected <index>/<count>, got '{}'", s))?;
+        let index: u64 = index
+            .parse()
+            .map_err(|e| format!("bad shard index: {}", e))?;
+        let count: u64 = count
+            .parse()
+            .map_err(|e| format!("bad shard count: {}", e))?;
+        if count == 0 || index >= count {
+            return Err(format!(
+                "shard index must be in 0..{}, got {}",
+                count, index
+            ));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Enumerates entropy as `secret_base + shard_offset + counter` (mod 2^256).
+///
+/// Job `j` covers counters `j * stride .. (j + 1) * stride`, so every job, on
+/// every machine sharing the same secret base, tests a disjoint range.
+#[derive(Clone)]
+pub struct CounterNumberGenerator {
+    start: Entropy,
+    stride: u128,
+    counter: u128,
+}
+
+impl CounterNumberGenerator {
+    /// `stride` is the number of values drawn per job and must match what the
+    /// searcher actually consumes, or neighbouring jobs will overlap.
+    pub fn new(secret_base: &Entropy, shard: Shard, stride: u64) -> Self {
+        let start = BigUint::from_bytes_be(secret_base) + shard.offset();
+        Self {
+            start: to_entropy(&start),
+            stride: stride as u128,
+            counter: 0,
+        }
+    }
+
+    /// Whether `num_jobs` jobs of `stride` values fit inside a single shard
+    /// without spilling into the next.
+    pub fn fits(shard: Shard, stride: u64, num_jobs: usize) -> bool {
+        BigUint::from(stride) * num_jobs <= shard.width()
+    }
+}
+
+impl NumberGenerator for CounterNumberGenerator {
+    fn generate(&mut self) -> SecretEntropy {
+        let entropy = Zeroizing::new(add_u128(&self.start, self.counter));
+        self.counter += 1;
+        entropy
+    }
+
+    fn start_job(&mut self, job: usize) {
+        self.counter = job as u128 * self.stride;
+    }
+
+    fn job_range(&self, jobs: Range<usize>) -> Option<(Entropy, Entropy)> {
+        Some((
+            add_u128(&self.start, jobs.start as u128 * self.stride),
+            add_u128(&self.start, jobs.end as u128 * self.stride),
+        ))
+    }
+
+    fn clone_box(&self) -> Box<dyn NumberGenerator + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl Drop for CounterNumberGenerator {
+    fn drop(&mut self) {
+        self.start.zeroize();
+    }
+}
+
+/// Reduce a number mod 2^256 into big-endian entropy bytes.
+fn to_entropy(n: &BigUint) -> Entropy {
+    let bytes = n.to_bytes_le();
+    let mut entropy = [0u8; 32];
+    for (i, b) in bytes.iter().take(32).enumerate() {
+        entropy[31 - i] = *b;
+    }
+    entropy
+}
+
+/// Big-endian `a + n` (mod 2^256).
+fn add_u128(a: &Entropy, n: u128) -> Entropy {
+    let mut out = *a;
+    let mut carry = n;
+    for byte in out.iter_mut().rev() {
+        if carry == 0 {
+            break;
+        }
+        let sum = *byte as u128 + (carry & 0xff);
+        *byte = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_u128_carries_past_the_low_bytes() {
+        let mut a = [0u8; 32];
+        a[31] = 0xff;
+        let mut expected = [0u8; 32];
+        expected[30] = 1;
+        assert_eq!(add_u128(&a, 1), expected);
+
+        // a carry out of the low 16 bytes, which `n` alone can't reach
+        let mut a = [0u8; 32];
+        a[16..].fill(0xff);
+        let mut expected = [0u8; 32];
+        expected[15] = 1;
+        assert_eq!(add_u128(&a, 1), expected);
+
+        let mut a = [0u8; 32];
+        a[31] = 1;
+        assert_eq!(add_u128(&a, u128::MAX), expected);
+
+        let mut a = [0xab; 32];
+        a[31] = 0;
+        let mut expected = a;
+        expected[31] = 7;
+        assert_eq!(add_u128(&a, 7), expected);
+    }
+
+    #[test]
+    fn shards_parse_as_index_over_count() {
+        assert_eq!("3/8".parse(), Ok(Shard { index: 3, count: 8 }));
+        assert_eq!(
+            "8/8".parse::<Shard>().err().as_deref(),
+            Some("shard index must be in 0..8, got 8")
+        );
+        assert_eq!(
+            "0/0".parse::<Shard>().err().as_deref(),
+            Some("shard index must be in 0..0, got 0")
+        );
+        assert_eq!(
+            "3".parse::<Shard>().err().as_deref(),
+            Some("expected <index>/<count>, got '3'")
+        );
+        assert!("x/8"
+            .parse::<Shard>()
+            .is_err_and(|e| e.starts_with("bad shard index")));
+        assert!("1/-8"
+            .parse::<Shard>()
+            .is_err_and(|e| e.starts_with("bad shard count")));
+    }
+
+    #[test]
+    fn shards_split_the_keyspace_evenly() {
+        let shard = Shard { index: 1, count: 4 };
+        assert_eq!(shard.offset(), BigUint::one() << 254);
+        assert!(CounterNumberGenerator::fits(shard, 1_001, 1_000_000_000));
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    io::{self, BufRead},
+    path::Path,
+    sync::Mutex,
+};
+
+use k256::{PublicKey, SecretKey};
+use rusqlite::{params, Connection};
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::{
+    criteria::{CriteriaPredicate, Score},
+    crypto::to_checksum_address,
+    pattern::PatternCriteria,
+    save::{RunInfo, SaveRecord, SaveSink},
+    seal,
+};
+
+/// Database `sqlite` sinks and the `results` subcommand use by default.
+pub const RESULTS_DB: &str = "/var/log/vanitygen/results.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        machine TEXT NOT NULL,
+        config TEXT NOT NULL,
+        started TEXT NOT NULL,
+        stopped TEXT,
+        attempts INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        address TEXT NOT NULL,
+        generator TEXT NOT NULL,
+        derivation_kind TEXT NOT NULL,
+        derivation TEXT,
+        score TEXT NOT NULL,
+        headline INTEGER NOT NULL,
+        matched TEXT NOT NULL,
+        attempts INTEGER NOT NULL,
+        found TEXT NOT NULL,
+        sealed_secret TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS results_headline ON results(headline);
+";
+
+fn open_db(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    // several machines may share the file over a network mount
+    conn.busy_timeout(std::time::Duration::from_secs(30))?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+/// Saved results in a SQLite database, one row per result plus one per run.
+///
+/// Secrets are only ever stored sealed to the `--results-key` public key; the
+/// machine running the search can't read them back.
+pub struct SqliteSink {
+    conn: Mutex<Connection>,
+    run_id: i64,
+    key: PublicKey,
+}
+
+impl SqliteSink {
+    /// Open (or create) the database at `path` and record the start of a run.
+    pub fn open(path: &Path, run: &RunInfo) -> io::Result<Self> {
+        let key = run.results_key.ok_or_else(|| {
+            io::Error::other("sqlite sinks need --results-key to seal secrets to")
+        })?;
+        let conn = open_db(path).map_err(io::Error::other)?;
+        conn.execute(
+            "INSERT INTO runs (machine, config, started) VALUES (?1, ?2, ?3)",
+            params![run.machine, run.config, now()],
+        )
+        .map_err(io::Error::other)?;
+        let run_id = conn.last_insert_rowid();
+        Ok(Self {
+            conn: Mutex::new(conn),
+            run_id,
+            key,
+        })
+    }
+}
+
+impl SaveSink for SqliteSink {
+    fn save(&self, record: &SaveRecord) -> io::Result<()> {
+        let (derivation_kind, derivation) = &record.derivation;
+        // a CREATE2 salt is the secret itself, so it's only kept sealed
+        let derivation = (derivation.as_str() != record.secret).then_some(derivation.as_str());
+        let sealed_secret = seal::seal(&self.key, record.secret.as_bytes());
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO results (run_id, address, generator, derivation_kind, derivation,
+                    score, headline, matched, attempts, found, sealed_secret)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    self.run_id,
+                    record.address,
+                    record.generator,
+                    derivation_kind,
+                    derivation,
+                    json!(record.score.0).to_string(),
+                    record.score.headline() as i64,
+                    record.tag,
+                    record.attempts as i64,
+                    now(),
+                    sealed_secret,
+                ],
+            )
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn finish(&self, attempts: usize) -> io::Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE runs SET stopped = ?1, attempts = ?2 WHERE id = ?3",
+                params![now(), attempts as i64, self.run_id],
+            )
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+}
+
+/// Which stored results to show.
+#[derive(Default)]
+pub struct Filter {
+    pub run: Option<i64>,
+    /// Smallest first score part, e.g. the number of leading zero nibbles.
+    pub min_score: Option<u64>,
+    /// Only results fully matching this pattern.
+    pub pattern: Option<PatternCriteria>,
+}
+
+impl Filter {
+    fn matches(&self, address: &str) -> bool {
+        self.pattern.as_ref().is_none_or(|pattern| {
+            pattern
+                .done()
+                .is_some_and(|done| pattern.score(address).reaches(&done))
+        })
+    }
+}
+
+struct Stored {
+    id: i64,
+    run_id: i64,
+    address: String,
+    generator: String,
+    derivation_kind: String,
+    derivation: Option<String>,
+    score: Score,
+    matched: String,
+    attempts: i64,
+    found: String,
+    sealed_secret: String,
+}
+
+fn query(conn: &Connection, filter: &Filter) -> rusqlite::Result<Vec<Stored>> {
+    let mut statement = conn.prepare(
+        "SELECT id, run_id, address, generator, derivation_kind, derivation, score, matched,
+                attempts, found, sealed_secret
+         FROM results
+         WHERE (?1 IS NULL OR run_id = ?1) AND (?2 IS NULL OR headline >= ?2)
+         ORDER BY id",
+    )?;
+    let rows = statement.query_map(
+        params![filter.run, filter.min_score.map(|score| score as i64)],
+        |row| {
+            let score: String = row.get(6)?;
+            Ok(Stored {
+                id: row.get(0)?,
+                run_id: row.get(1)?,
+                address: row.get(2)?,
+                generator: row.get(3)?,
+                derivation_kind: row.get(4)?,
+                derivation: row.get(5)?,
+                score: Score(serde_json::from_str(&score).unwrap_or_default()),
+                matched: row.get(7)?,
+                attempts: row.get(8)?,
+                found: row.get(9)?,
+                sealed_secret: row.get(10)?,
+            })
+        },
+    )?;
+    let mut results = Vec::new();
+    for row in rows {
+        let row = row?;
+        if filter.matches(&row.address) {
+            results.push(row);
+        }
+    }
+    Ok(results)
+}
+
+fn open_existing(path: &Path) -> Result<Connection, String> {
+    if !path.exists() {
+        return Err(format!("no results database at {}", path.display()));
+    }
+    open_db(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))
+}
+
+/// Print the stored results matching `filter`, without their secrets.
+pub fn list(path: &Path, filter: &Filter) -> Result<(), String> {
+    let conn = open_existing(path)?;
+    let results = query(&conn, filter).map_err(|e| e.to_string())?;
+    for result in &results {
+        println!(
+            "#{:<6} run {:<4} {}     score {:<3} {:<9} {}     {}",
+            result.id,
+            result.run_id,
+            to_checksum_address(&result.address),
+            result.score,
+            result.generator,
+            result.matched,
+            result.found
+        );
+    }
+    println!("{} results", results.len());
+    Ok(())
+}
+
+/// Print every run with its machine, times, attempts and number of results.
+pub fn runs(path: &Path, with_config: bool) -> Result<(), String> {
+    let conn = open_existing(path)?;
+    let mut statement = conn
+        .prepare(
+            "SELECT runs.id, machine, config, started, stopped, runs.attempts, COUNT(results.id)
+             FROM runs LEFT JOIN results ON results.run_id = runs.id
+             GROUP BY runs.id ORDER BY runs.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rows = statement.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let read = || -> rusqlite::Result<_> {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        };
+        let (id, machine, config, started, stopped, attempts, results) =
+            read().map_err(|e| e.to_string())?;
+        println!(
+            "run {:<4} {:<16} {} .. {}     {} attempts     {} results",
+            id,
+            machine,
+            started,
+            stopped.as_deref().unwrap_or("(running or killed)"),
+            attempts.map_or("?".to_string(), |a| a.to_string()),
+            results
+        );
+        if with_config {
+            for line in config.lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the stored results matching `filter` as JSON Lines, with their
+/// secrets opened by `secret_key` or else still sealed.
+pub fn export(path: &Path, filter: &Filter, secret_key: Option<&SecretKey>) -> Result<(), String> {
+    let conn = open_existing(path)?;
+    let results = query(&conn, filter).map_err(|e| e.to_string())?;
+    for result in results {
+        let mut line = json!({
+            "id": result.id,
+            "run": result.run_id,
+            "address": result.address,
+            "checksum_address": to_checksum_address(&result.address),
+            "generator": result.generator,
+            "score": result.score.0,
+            "matched": result.matched,
+            "attempts": result.attempts,
+            "timestamp": result.found,
+        });
+        if let Some(derivation) = &result.derivation {
+            line[result.derivation_kind.as_str()] = json!(derivation);
+        }
+        match secret_key {
+            Some(secret_key) => {
+                let secret = seal::open(secret_key, &result.sealed_secret)
+                    .map_err(|e| format!("result #{}: {}", result.id, e))?;
+                line["secret"] = json!(String::from_utf8_lossy(&secret));
+            }
+            None => line["sealed_secret"] = json!(result.sealed_secret),
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Print each line of stdin with the sealed secret in its last field opened,
+/// e.g. `1 0xAbC… <sealed>` from a sealed leaderboard file.
+pub fn open_lines(secret_key: &SecretKey) -> Result<(), String> {
+    for (number, line) in io::stdin().lock().lines().enumerate() {
+        let line = line.map_err(|e| format!("failed to read stdin: {}", e))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (start, sealed) = line.rsplit_once(' ').unwrap_or(("", line));
+        let secret =
+            seal::open(secret_key, sealed).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        let secret = Zeroizing::new(String::from_utf8_lossy(&secret).into_owned());
+        if start.is_empty() {
+            println!("{}", *secret);
+        } else {
+            println!("{} {}", start, *secret);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database holding two runs: run 1 found `0xdead…` (headline 4) and
+    /// `0x00ab…` (headline 1), run 2 found `0xbeef…` (headline 2).
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        for run in [1, 2] {
+            conn.execute(
+                "INSERT INTO runs (id, machine, config, started) VALUES (?1, 'test', '', ?2)",
+                params![run, now()],
+            )
+            .unwrap();
+        }
+        for (run_id, address, headline) in [
+            (1, format!("0xdead{}", "0".repeat(36)), 4),
+            (1, format!("0x00ab{}", "1".repeat(36)), 1),
+            (2, format!("0xbeef{}", "2".repeat(36)), 2),
+        ] {
+            conn.execute(
+                "INSERT INTO results (run_id, address, generator, derivation_kind, derivation,
+                    score, headline, matched, attempts, found, sealed_secret)
+                 VALUES (?1, ?2, 'mnemonic', 'phrase', NULL, ?3, ?4, 'best', 1, ?5, '')",
+                params![
+                    run_id,
+                    address,
+                    json!([headline]).to_string(),
+                    headline,
+                    now()
+                ],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn addresses(conn: &Connection, filter: &Filter) -> Vec<String> {
+        query(conn, filter)
+            .unwrap()
+            .into_iter()
+            .map(|stored| stored.address[..6].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn query_without_a_filter_returns_everything() {
+        let conn = database();
+        assert_eq!(
+            addresses(&conn, &Filter::default()),
+            ["0xdead", "0x00ab", "0xbeef"]
+        );
+    }
+
+    #[test]
+    fn query_filters_by_run_score_and_pattern() {
+        let conn = database();
+        let by_run = Filter {
+            run: Some(1),
+            ..Filter::default()
+        };
+        assert_eq!(addresses(&conn, &by_run), ["0xdead", "0x00ab"]);
+
+        let by_score = Filter {
+            min_score: Some(2),
+            ..Filter::default()
+        };
+        assert_eq!(addresses(&conn, &by_score), ["0xdead", "0xbeef"]);
+
+        let by_pattern = Filter {
+            pattern: Some(PatternCriteria::new("^beef", false).unwrap()),
+            ..Filter::default()
+        };
+        assert_eq!(addresses(&conn, &by_pattern), ["0xbeef"]);
+
+        let combined = Filter {
+            run: Some(1),
+            min_score: Some(2),
+            pattern: Some(PatternCriteria::new("^[d0]", false).unwrap()),
+        };
+        assert_eq!(addresses(&conn, &combined), ["0xdead"]);
+    }
+}
@@ -0,0 +1,400 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use k256::PublicKey;
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::{
+    criteria::Score,
+    crypto::to_checksum_address,
+    dictionary::DictionaryCriteria,
+    mnemonic_log,
+    results::{SqliteSink, RESULTS_DB},
+};
+
+/// Decides whether a found address is worth saving, independently of the search criteria.
+///
+/// Written as `|`-separated alternatives of `&`-joined terms, e.g.
+/// `zeros:8|prefix:dead&suffix:beef`. Terms are:
+///
+/// * `zeros:<n>` - at least `n` leading zero nibbles
+/// * `prefix:<hex>`, `suffix:<hex>`, `contains:<hex>` - fixed hex digits
+/// * `none` - never save
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveRule {
+    LeadingZeros(usize),
+    Prefix(String),
+    Suffix(String),
+    Contains(String),
+    Never,
+    All(Vec<SaveRule>),
+    Any(Vec<SaveRule>),
+}
+
+impl SaveRule {
+    /// If `address` should be saved, the term that matched, used to tag the saved result.
+    pub fn matches(&self, address: &str) -> Option<String> {
+        let hex = address.trim_start_matches("0x");
+        match self {
+            Self::LeadingZeros(n) => {
+                (hex.chars().take_while(|c| *c == '0').count() >= *n).then(|| self.to_string())
+            }
+            Self::Prefix(p) => hex.starts_with(p.as_str()).then(|| self.to_string()),
+            Self::Suffix(p) => hex.ends_with(p.as_str()).then(|| self.to_string()),
+            Self::Contains(p) => hex.contains(p.as_str()).then(|| self.to_string()),
+            Self::Never => None,
+            Self::All(rules) => rules
+                .iter()
+                .map(|r| r.matches(address))
+                .collect::<Option<Vec<_>>>()
+                .map(|tags| tags.join("&")),
+            Self::Any(rules) => rules.iter().find_map(|r| r.matches(address)),
+        }
+    }
+}
+
+impl Default for SaveRule {
+    /// The historical rule: eight leading zero nibbles, i.e. `0x00000000...`.
+    fn default() -> Self {
+        Self::LeadingZeros(8)
+    }
+}
+
+impl FromStr for SaveRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut any = s
+            .split('|')
+            .map(|alternative| {
+                let mut all = alternative
+                    .split('&')
+                    .map(|term| parse_term(term.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(if all.len() == 1 {
+                    all.remove(0)
+                } else {
+                    Self::All(all)
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Self::Any(any)
+        })
+    }
+}
+
+fn parse_term(term: &str) -> Result<SaveRule, String> {
+    if term == "none" {
+        return Ok(SaveRule::Never);
+    }
+    let (kind, value) = term
+        .split_once(':')
+        .ok_or_else(|| format!("expected <kind>:<value>, got '{}'", term))?;
+    let hex = || -> Result<String, String> {
+        let value = value.to_ascii_lowercase();
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("'{}' is not a hex pattern", value));
+        }
+        Ok(value)
+    };
+    match kind {
+        "zeros" => value
+            .parse()
+            .map(SaveRule::LeadingZeros)
+            .map_err(|e| format!("bad zero count '{}': {}", value, e)),
+        "prefix" => hex().map(SaveRule::Prefix),
+        "suffix" => hex().map(SaveRule::Suffix),
+        "contains" => hex().map(SaveRule::Contains),
+        _ => Err(format!("unknown save rule '{}'", kind)),
+    }
+}
+
+impl fmt::Display for SaveRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LeadingZeros(n) => write!(f, "zeros:{}", n),
+            Self::Prefix(p) => write!(f, "prefix:{}", p),
+            Self::Suffix(p) => write!(f, "suffix:{}", p),
+            Self::Contains(p) => write!(f, "contains:{}", p),
+            Self::Never => write!(f, "none"),
+            Self::All(rules) => write_joined(f, rules, "&"),
+            Self::Any(rules) => write_joined(f, rules, "|"),
+        }
+    }
+}
+
+fn write_joined(f: &mut fmt::Formatter<'_>, rules: &[SaveRule], sep: &str) -> fmt::Result {
+    for (i, rule) in rules.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", sep)?;
+        }
+        write!(f, "{}", rule)?;
+    }
+    Ok(())
+}
+
+/// A saved result as handed to every sink.
+pub struct SaveRecord<'a> {
+    /// Lowercase `0x`-prefixed address.
+    pub address: &'a str,
+    /// Mnemonic phrase, CREATE2 salt, ... as given by the address generator.
+    pub secret: &'a str,
+    /// Which address generator found it, e.g. `mnemonic` or `create2`.
+    pub generator: &'static str,
+    /// Derivation path, CREATE2 salt, ... labelled by what it is.
+    pub derivation: (&'static str, Zeroizing<String>),
+    pub score: &'a Score,
+    /// The save rule term or dictionary word the address matched.
+    pub tag: &'a str,
+    /// Attempts the whole search had made when it was found.
+    pub attempts: usize,
+}
+
+/// Somewhere saved results are written to.
+pub trait SaveSink: Send + Sync {
+    fn save(&self, record: &SaveRecord) -> io::Result<()>;
+
+    /// Called once when the search ends, with the attempts it made.
+    fn finish(&self, _attempts: usize) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What sinks that keep run metadata know about the search.
+pub struct RunInfo {
+    /// The effective options, as printed by `search --print-config`.
+    pub config: String,
+    pub machine: String,
+    /// Public key that secrets are sealed to, for sinks that only store them encrypted.
+    pub results_key: Option<PublicKey>,
+}
+
+impl RunInfo {
+    pub fn new(config: String, results_key: Option<PublicKey>) -> Self {
+        Self {
+            config,
+            machine: hostname(),
+            results_key,
+        }
+    }
+}
+
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        // SAFETY: the buffer is valid for its whole length, and one byte is kept
+        // back so that a truncated name is still NUL-terminated.
+        if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len() - 1) } == 0 {
+            let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            return String::from_utf8_lossy(&name[..len]).into_owned();
+        }
+    }
+    std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// `<address> <secret>` lines in `mnemonics.log`, via the mnemonic logger.
+pub struct MnemonicLogSink;
+
+impl SaveSink for MnemonicLogSink {
+    fn save(&self, record: &SaveRecord) -> io::Result<()> {
+        mnemonic_log!("{} {}", to_checksum_address(record.address), record.secret);
+        Ok(())
+    }
+}
+
+/// Open `path` for appending, readable only by its owner.
+fn open_private(path: &Path) -> io::Result<Mutex<File>> {
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    Ok(Mutex::new(options.open(path)?))
+}
+
+/// `<address> <secret>` lines appended to a file readable only by its owner.
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: open_private(path)?,
+        })
+    }
+}
+
+impl SaveSink for FileSink {
+    fn save(&self, record: &SaveRecord) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        writeln!(
+            file,
+            "{} {}",
+            to_checksum_address(record.address),
+            record.secret
+        )?;
+        file.flush()
+    }
+}
+
+/// One JSON object per line with everything known about the result, appended
+/// to a file readable only by its owner.
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: open_private(path)?,
+        })
+    }
+}
+
+impl SaveSink for JsonLinesSink {
+    fn save(&self, record: &SaveRecord) -> io::Result<()> {
+        let (derivation_kind, derivation) = &record.derivation;
+        let mut line = json!({
+            "address": record.address,
+            "checksum_address": to_checksum_address(record.address),
+            "generator": record.generator,
+            "score": record.score.0,
+            "matched": record.tag,
+            "attempts": record.attempts,
+            "timestamp": chrono::Local::now().to_rfc3339(),
+        });
+        // a CREATE2 salt is the secret itself, which is only written below
+        if derivation.as_str() != record.secret {
+            line[*derivation_kind] = json!(derivation.as_str());
+        }
+        let mut head = line.to_string();
+        head.pop();
+
+        // the secret is escaped straight into a buffer that is wiped afterwards,
+        // sized up front so that growing it leaves no copies behind
+        let mut text = Zeroizing::new(Vec::with_capacity(
+            head.len() + record.secret.len() * 6 + 16,
+        ));
+        text.extend_from_slice(head.as_bytes());
+        text.extend_from_slice(b",\"secret\":");
+        serde_json::to_writer(&mut *text, record.secret)?;
+        text.extend_from_slice(b"}\n");
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&text)?;
+        file.flush()
+    }
+}
+
+/// Where to send saved results, written `mnemonics`, `file:<path>`,
+/// `jsonl:<path>` or `sqlite[:<path>]`.
+#[derive(Clone, Debug)]
+pub enum SinkSpec {
+    MnemonicLog,
+    File(PathBuf),
+    JsonLines(PathBuf),
+    Sqlite(PathBuf),
+}
+
+impl SinkSpec {
+    pub fn open(&self, run: &RunInfo) -> io::Result<Box<dyn SaveSink>> {
+        Ok(match self {
+            Self::MnemonicLog => Box::new(MnemonicLogSink),
+            Self::File(path) => Box::new(FileSink::open(path)?),
+            Self::JsonLines(path) => Box::new(JsonLinesSink::open(path)?),
+            Self::Sqlite(path) => Box::new(SqliteSink::open(path, run)?),
+        })
+    }
+}
+
+impl FromStr for SinkSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = s.split_once(':').unwrap_or((s, ""));
+        match (kind, path) {
+            ("mnemonics", "") => Ok(Self::MnemonicLog),
+            ("file", path) if !path.is_empty() => Ok(Self::File(PathBuf::from(path))),
+            ("jsonl", path) if !path.is_empty() => Ok(Self::JsonLines(PathBuf::from(path))),
+            ("sqlite", "") => Ok(Self::Sqlite(PathBuf::from(RESULTS_DB))),
+            ("sqlite", path) => Ok(Self::Sqlite(PathBuf::from(path))),
+            _ => Err(format!(
+                "expected 'mnemonics', 'file:<path>', 'jsonl:<path>' or 'sqlite[:<path>]', got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MnemonicLog => write!(f, "mnemonics"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::JsonLines(path) => write!(f, "jsonl:{}", path.display()),
+            Self::Sqlite(path) => write!(f, "sqlite:{}", path.display()),
+        }
+    }
+}
+
+/// A save rule together with the sinks that matching results go to.
+pub struct Saver {
+    pub rule: SaveRule,
+    /// Also save addresses spelling a dictionary word of at least this many letters.
+    pub words: Option<(DictionaryCriteria, usize)>,
+    pub sinks: Vec<Box<dyn SaveSink>>,
+}
+
+impl Saver {
+    /// The tag to save `address` under, if the rule or a long enough word matches.
+    pub fn matches(&self, address: &str) -> Option<String> {
+        self.rule.matches(address).or_else(|| {
+            let (criteria, min_len) = self.words.as_ref()?;
+            let found = criteria.longest_word(address)?;
+            (found.word.len() >= *min_len).then(|| format!("word:{}", found.word))
+        })
+    }
+
+    /// Write a result to every sink, carrying on past (and reporting) failures.
+    pub fn save(&self, record: &SaveRecord) -> io::Result<()> {
+        let mut result = Ok(());
+        for sink in &self.sinks {
+            if let Err(e) = sink.save(record) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Tell every sink the search has ended, carrying on past (and reporting) failures.
+    pub fn finish(&self, attempts: usize) -> io::Result<()> {
+        let mut result = Ok(());
+        for sink in &self.sinks {
+            if let Err(e) = sink.finish(attempts) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+impl Default for Saver {
+    fn default() -> Self {
+        Self {
+            rule: SaveRule::default(),
+            words: None,
+            sinks: vec![Box::new(MnemonicLogSink)],
+        }
+    }
+}
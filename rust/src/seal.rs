@@ -0,0 +1,169 @@
+use std::{fs, path::Path};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use k256::{
+    ecdh::EphemeralSecret,
+    elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint},
+    EncodedPoint, PublicKey, SecretKey,
+};
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+const POINT_LEN: usize = 33;
+const IV_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+
+/// Encryption and MAC keys for one sealed secret, from the ECDH shared secret.
+fn keys(shared_secret: &[u8], ephemeral: &[u8]) -> Zeroizing<[u8; 48]> {
+    let mut keys = Zeroizing::new([0u8; 48]);
+    Hkdf::<Sha256>::new(Some(ephemeral), shared_secret)
+        .expand(b"vanitygen sealed secret", keys.as_mut())
+        .expect("48 bytes is a valid HKDF-SHA256 output length");
+    keys
+}
+
+fn mac(mac_key: &[u8], data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(mac_key).expect("HMAC takes any key length");
+    mac.update(data);
+    mac
+}
+
+/// Encrypt `secret` so that only the holder of `recipient`'s secret key can
+/// read it: ECIES over secp256k1 with HKDF-SHA256, AES-128-CTR and HMAC-SHA256.
+///
+/// Returns hex of `ephemeral public key ++ iv ++ ciphertext ++ mac`.
+pub fn seal(recipient: &PublicKey, secret: &[u8]) -> String {
+    let ephemeral = EphemeralSecret::random(&mut rand::rngs::OsRng);
+    let ephemeral_point = ephemeral.public_key().to_encoded_point(true);
+    let shared = ephemeral.diffie_hellman(recipient);
+    let keys = keys(shared.raw_secret_bytes(), ephemeral_point.as_bytes());
+
+    let mut sealed = ephemeral_point.as_bytes().to_vec();
+    let mut iv = [0u8; IV_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut iv);
+    sealed.extend_from_slice(&iv);
+    let start = sealed.len();
+    sealed.extend_from_slice(secret);
+    Aes128Ctr::new(keys[..16].into(), &iv.into()).apply_keystream(&mut sealed[start..]);
+    let tag = mac(&keys[16..], &sealed[POINT_LEN..])
+        .finalize()
+        .into_bytes();
+    sealed.extend_from_slice(&tag);
+    hex::encode(sealed)
+}
+
+/// Decrypt a secret sealed to the public key of `secret_key`.
+pub fn open(secret_key: &SecretKey, sealed: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let sealed = hex::decode(sealed).map_err(|_| "sealed secret is not hex".to_string())?;
+    if sealed.len() < POINT_LEN + IV_LEN + MAC_LEN {
+        return Err("sealed secret is too short".into());
+    }
+    let (ephemeral, rest) = sealed.split_at(POINT_LEN);
+    let (body, tag) = rest.split_at(rest.len() - MAC_LEN);
+    let (iv, ciphertext) = body.split_at(IV_LEN);
+
+    let ephemeral_key = parse_point(ephemeral)?;
+    let shared =
+        k256::ecdh::diffie_hellman(secret_key.to_nonzero_scalar(), ephemeral_key.as_affine());
+    let keys = keys(shared.raw_secret_bytes(), ephemeral);
+    mac(&keys[16..], body)
+        .verify_slice(tag)
+        .map_err(|_| "sealed secret was not sealed to this key, or was altered".to_string())?;
+
+    let mut secret = Zeroizing::new(ciphertext.to_vec());
+    Aes128Ctr::new(keys[..16].into(), iv.into()).apply_keystream(&mut secret);
+    Ok(secret)
+}
+
+fn parse_point(bytes: &[u8]) -> Result<PublicKey, String> {
+    let point = EncodedPoint::from_bytes(bytes).map_err(|_| "not a SEC1 public key".to_string())?;
+    Option::from(PublicKey::from_encoded_point(&point))
+        .ok_or_else(|| "not a point on secp256k1".to_string())
+}
+
+/// Parse a hex SEC1 public key (compressed or not), as printed by `results keygen`.
+pub fn parse_public_key(s: &str) -> Result<PublicKey, String> {
+    let bytes = hex::decode(s.trim().trim_start_matches("0x"))
+        .map_err(|_| format!("'{}' is not a hex public key", s))?;
+    parse_point(&bytes).map_err(|e| format!("'{}' is {}", s, e))
+}
+
+/// The compressed hex form of `public_key`.
+pub fn format_public_key(public_key: &PublicKey) -> String {
+    hex::encode(public_key.to_encoded_point(true).as_bytes())
+}
+
+/// Read a secret key file written by `write_secret_key`.
+pub fn read_secret_key(path: &Path) -> Result<SecretKey, String> {
+    let contents = Zeroizing::new(
+        fs::read_to_string(path)
+            .map_err(|e| format!("failed to read secret key {}: {}", path.display(), e))?,
+    );
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    hex::decode_to_slice(contents.trim(), bytes.as_mut())
+        .map_err(|_| format!("{} is not a 64 hex character secret key", path.display()))?;
+    SecretKey::from_be_bytes(bytes.as_ref())
+        .map_err(|_| format!("{} is not a valid secp256k1 secret key", path.display()))
+}
+
+/// Create a key pair, writing the secret key to a new file readable only by its
+/// owner and returning the public key.
+pub fn write_secret_key(path: &Path) -> Result<PublicKey, String> {
+    use std::io::Write;
+
+    let secret_key = SecretKey::random(&mut rand::rngs::OsRng);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let encoded = Zeroizing::new(hex::encode(secret_key.to_be_bytes()));
+    writeln!(file, "{}", *encoded)
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(secret_key.public_key())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_pair() -> (SecretKey, PublicKey) {
+        let secret_key = SecretKey::random(&mut rand::rngs::OsRng);
+        let public_key = secret_key.public_key();
+        (secret_key, public_key)
+    }
+
+    #[test]
+    fn open_returns_what_was_sealed() {
+        let (secret_key, public_key) = key_pair();
+        let sealed = seal(&public_key, b"abandon ability able");
+        assert_eq!(
+            open(&secret_key, &sealed).unwrap().as_slice(),
+            b"abandon ability able"
+        );
+    }
+
+    #[test]
+    fn open_rejects_an_altered_secret() {
+        let (secret_key, public_key) = key_pair();
+        let mut sealed = hex::decode(seal(&public_key, b"abandon ability able")).unwrap();
+        // flip a bit of the ciphertext, past the ephemeral key and iv
+        sealed[POINT_LEN + IV_LEN] ^= 1;
+        assert!(open(&secret_key, &hex::encode(sealed)).is_err());
+    }
+
+    #[test]
+    fn open_rejects_another_key() {
+        let (_, public_key) = key_pair();
+        let (other_secret_key, _) = key_pair();
+        let sealed = seal(&public_key, b"abandon ability able");
+        assert!(open(&other_secret_key, &sealed).is_err());
+    }
+}
@@ -1,32 +1,66 @@
-use crate::criteria::CriteriaPredicate;
-use crate::crypto::AddressGenerator;
-use crate::mnemonic_log;
-use crate::randnum::NumberGenerator;
-use log::info;
+use crate::coverage_log;
+use crate::criteria::{CriteriaPredicate, Score};
+use crate::crypto::{to_checksum_address, AddressGenerator};
+use crate::leaderboard::{persist, Leaderboard, LEADERBOARD_FILE};
+use crate::randnum::{Entropy, NumberGenerator};
+use crate::save::{SaveRecord, Saver};
+use crate::secret::Locked;
+use k256::PublicKey;
+use log::{error, info, warn};
 use num_format::{Locale, ToFormattedString};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
 
-use bip32::{Language, Mnemonic};
-use rayon::{current_thread_index, prelude::*, ThreadPool, ThreadPoolBuilder};
+use rayon::{current_thread_index, prelude::*, ThreadPool};
 
-pub struct Searcher<'a> {
-    number_generator: Box<dyn NumberGenerator + 'a>,
-    address_generator: Box<dyn AddressGenerator + 'a>,
-    criteria_predicate: Box<dyn CriteriaPredicate + 'a>,
+pub struct Searcher<N, A, C> {
+    number_generator: N,
+    address_generator: A,
+    criteria_predicate: C,
     max_attempts: usize,
+    stop_when_done: bool,
 }
 
 pub struct SearchResult {
     pub address: String,
-    pub seed: [u8; 32],
+    pub score: Score,
+    /// Kept in its own locked page and wiped when the result is replaced or dropped.
+    pub seed: Locked<Entropy>,
 }
 
-impl<'a> Searcher<'a> {
+/// A candidate the save rule matched. It only lives until the job's saves are
+/// written, so its seed is boxed and wiped on drop rather than given a locked
+/// page of its own.
+pub struct Saved {
+    pub address: String,
+    pub score: Score,
+    pub seed: Box<Zeroizing<Entropy>>,
+}
+
+/// What one job found.
+pub struct JobResult {
+    pub best: SearchResult,
+    /// Every candidate the save rule matched, with the tag it matched under.
+    pub saves: Vec<(String, Saved)>,
+    /// Candidates generated, fewer than `max_attempts + 1` if the job stopped early.
+    pub attempts: usize,
+}
+
+impl<N, A, C> Searcher<N, A, C>
+where
+    N: NumberGenerator,
+    A: AddressGenerator,
+    C: CriteriaPredicate,
+{
     pub fn new(
-        number_generator: Box<dyn NumberGenerator + 'a>,
-        address_generator: Box<dyn AddressGenerator + 'a>,
-        criteria_predicate: Box<dyn CriteriaPredicate + 'a>,
+        number_generator: N,
+        address_generator: A,
+        criteria_predicate: C,
         max_attempts: usize,
     ) -> Self {
         Self {
@@ -34,131 +68,691 @@ impl<'a> Searcher<'a> {
             address_generator,
             criteria_predicate,
             max_attempts,
+            stop_when_done: true,
         }
     }
 
-    pub fn run(&mut self) -> SearchResult {
-        let input_num = self.number_generator.generate();
-        let address = self.address_generator.generate(input_num).unwrap();
-        let mut best: SearchResult = SearchResult {
-            address,
-            seed: input_num,
-        };
-        for _ in 0..self.max_attempts {
+    /// Make every attempt even after a full match, so that the job covers its
+    /// whole range of the keyspace.
+    pub fn run_to_end(mut self) -> Self {
+        self.stop_when_done = false;
+        self
+    }
+
+    /// Keep the best of `max_attempts + 1` candidates, stopping early at a full match,
+    /// along with every candidate that `saver` matches.
+    pub fn run(&mut self, saver: &Saver) -> JobResult {
+        let done = self
+            .criteria_predicate
+            .done()
+            .filter(|_| self.stop_when_done);
+        let mut best: Option<(String, Score)> = None;
+        // the job's one locked slot: each improvement is copied into it, and it
+        // becomes the seed of the job's result
+        let mut best_seed = Locked::new([0; 32]);
+        let mut saves = Vec::new();
+        let mut attempts = 0;
+        for _ in 0..=self.max_attempts {
+            if best
+                .as_ref()
+                .zip(done.as_ref())
+                .is_some_and(|((_, score), done)| score.reaches(done))
+            {
+                break;
+            }
+            // dropping `entropy` wipes the losing candidate
             let entropy = self.number_generator.generate();
-            let address = self.address_generator.generate(entropy).unwrap();
-            if self.criteria_predicate.better(&address, &best.address) {
-                best = SearchResult {
-                    address,
-                    seed: entropy,
-                };
+            attempts += 1;
+            let address = self.address_generator.generate(&entropy).unwrap();
+            let score = self.criteria_predicate.score(&address);
+            if let Some(tag) = saver.matches(&address) {
+                saves.push((
+                    tag,
+                    Saved {
+                        address: address.clone(),
+                        score: score.clone(),
+                        seed: Box::new(Zeroizing::new(*entropy)),
+                    },
+                ));
+            }
+            if best.as_ref().is_none_or(|(_, best)| score > *best) {
+                *best_seed = *entropy;
+                best = Some((address, score));
             }
         }
-        best
+        let (address, score) = best.expect("a job makes at least one attempt");
+        JobResult {
+            best: SearchResult {
+                address,
+                score,
+                seed: best_seed,
+            },
+            saves,
+            attempts,
+        }
+    }
+}
+
+/// Tracks completed jobs so that contiguous covered ranges can be recorded.
+#[derive(Default)]
+struct Coverage {
+    /// Every job below this index has completed.
+    watermark: usize,
+    /// Completed jobs at or above the watermark.
+    pending: BTreeSet<usize>,
+    /// Jobs below this index have already been written to the coverage log.
+    logged: usize,
+}
+
+impl Coverage {
+    fn complete(&mut self, job: usize) {
+        self.pending.insert(job);
+        while self.pending.remove(&self.watermark) {
+            self.watermark += 1;
+        }
+    }
+
+    /// Take the newly covered range once it spans at least `min_jobs` jobs.
+    fn take(&mut self, min_jobs: usize) -> Option<Range<usize>> {
+        if self.watermark > self.logged && self.watermark - self.logged >= min_jobs {
+            let jobs = self.logged..self.watermark;
+            self.logged = self.watermark;
+            Some(jobs)
+        } else {
+            None
+        }
     }
 }
 
-pub struct ThreadPoolSearcher<'a> {
-    thread_pool: ThreadPool,
+/// Live counters of a running search, shared with whoever watches it, and a
+/// way to stop it early.
+pub struct SearchProgress {
+    completed_jobs: AtomicUsize,
+    /// Jobs taken off the queue, including ones skipped once the search ended.
+    started_jobs: AtomicUsize,
+    /// Candidates generated by completed jobs.
+    attempts: AtomicUsize,
+    /// The same, by thread pool index.
+    thread_attempts: Vec<AtomicUsize>,
+    saves: AtomicUsize,
+    /// Set by `run`.
+    num_jobs: AtomicUsize,
+    started: OnceLock<Instant>,
+    ended: OnceLock<Instant>,
+    cancelled: AtomicBool,
+    /// Address and score of the current best result.
+    best: Mutex<Option<(String, Score)>>,
+}
+
+impl SearchProgress {
+    /// Progress of a search on a thread pool of `num_threads` threads.
+    pub fn new(num_threads: usize) -> Self {
+        Self {
+            completed_jobs: AtomicUsize::new(0),
+            started_jobs: AtomicUsize::new(0),
+            attempts: AtomicUsize::new(0),
+            thread_attempts: (0..num_threads).map(|_| AtomicUsize::new(0)).collect(),
+            saves: AtomicUsize::new(0),
+            num_jobs: AtomicUsize::new(0),
+            started: OnceLock::new(),
+            ended: OnceLock::new(),
+            cancelled: AtomicBool::new(false),
+            best: Mutex::new(None),
+        }
+    }
+
+    pub fn attempts(&self) -> usize {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Attempts made on each thread of the pool, by thread index.
+    pub fn thread_attempts(&self) -> Vec<usize> {
+        self.thread_attempts
+            .iter()
+            .map(|attempts| attempts.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Jobs of the search not yet started. The rayon pool hands jobs out as
+    /// threads free up, so these are the search's remaining work, not a queue.
+    pub fn remaining_jobs(&self) -> usize {
+        let num_jobs = self.num_jobs.load(Ordering::Relaxed);
+        num_jobs.saturating_sub(self.started_jobs.load(Ordering::Relaxed))
+    }
+
+    pub fn saves(&self) -> usize {
+        self.saves.load(Ordering::Relaxed)
+    }
+
+    /// Time `run` has been running, or `None` before it starts.
+    pub fn elapsed(&self) -> Option<Duration> {
+        let started = self.started.get()?;
+        Some(
+            self.ended
+                .get()
+                .map_or_else(|| started.elapsed(), |ended| *ended - *started),
+        )
+    }
+
+    /// Skip every job that hasn't started yet; `run` returns once the running ones end.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    pub fn best(&self) -> Option<(String, Score)> {
+        self.best.lock().unwrap().clone()
+    }
+}
+
+pub struct ThreadPoolSearcher<N, A, C> {
+    thread_pool: Arc<ThreadPool>,
     num_jobs: usize,
     attempts_per_job: usize,
-    number_generator: Box<dyn NumberGenerator + Send + Sync + 'a>,
-    address_generator: Box<dyn AddressGenerator + Send + Sync + 'a>,
-    criteria_predicate: Box<dyn CriteriaPredicate + Send + Sync + 'a>,
+    leaderboard_size: usize,
+    number_generator: N,
+    address_generator: A,
+    criteria_predicate: C,
+    saver: Saver,
+    progress: Arc<SearchProgress>,
+    persist_leaderboard: bool,
+    sealed_to: Option<PublicKey>,
 }
 
-impl<'a> ThreadPoolSearcher<'a> {
+impl<N, A, C> ThreadPoolSearcher<N, A, C>
+where
+    N: NumberGenerator + Clone + Send + Sync,
+    A: AddressGenerator + Clone + Send + Sync,
+    C: CriteriaPredicate + Clone + Send + Sync,
+{
+    /// A search run on `thread_pool`, which several searches may share.
     pub fn new(
-        num_threads: usize,
+        thread_pool: Arc<ThreadPool>,
         num_jobs: usize,
         attempts_per_job: usize,
-        number_generator: Box<dyn NumberGenerator + Send + Sync + 'a>,
-        address_generator: Box<dyn AddressGenerator + Send + Sync + 'a>,
-        criteria_predicate: Box<dyn CriteriaPredicate + Send + Sync + 'a>,
+        leaderboard_size: usize,
+        number_generator: N,
+        address_generator: A,
+        criteria_predicate: C,
     ) -> Self {
-        let thread_pool = ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build()
-            .expect("Failed to create thread pool");
-
+        let progress = Arc::new(SearchProgress::new(thread_pool.current_num_threads()));
         Self {
             thread_pool,
             num_jobs,
             attempts_per_job,
+            leaderboard_size,
             number_generator,
             address_generator,
             criteria_predicate,
+            saver: Saver::default(),
+            progress,
+            persist_leaderboard: true,
+            sealed_to: None,
         }
     }
 
-    pub fn run(&self) -> String {
-        let best_address = Arc::new(Mutex::new(String::from(
-            "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
-        )));
-        let completed_jobs = Arc::new(AtomicUsize::new(0));
+    /// Replace the default save rule (`zeros:8` into `mnemonics.log`).
+    pub fn with_saver(mut self, saver: Saver) -> Self {
+        self.saver = saver;
+        self
+    }
+
+    /// Report progress to, and take cancellation from, `progress`.
+    pub fn with_progress(mut self, progress: Arc<SearchProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Keep the leaderboard, secrets included, in memory only instead of also
+    /// rewriting the leaderboard file on every change.
+    pub fn without_leaderboard_file(mut self) -> Self {
+        self.persist_leaderboard = false;
+        self
+    }
+
+    /// Seal the secrets in the leaderboard file to `key` rather than writing
+    /// them out in the clear.
+    pub fn with_sealed_leaderboard(mut self, key: PublicKey) -> Self {
+        self.sealed_to = Some(key);
+        self
+    }
+
+    pub fn run(&self) -> Leaderboard {
+        self.progress
+            .num_jobs
+            .store(self.num_jobs, Ordering::Relaxed);
+        self.progress.started.get_or_init(Instant::now);
+        let leaderboard = Arc::new(Mutex::new(Leaderboard::new(self.leaderboard_size)));
+        // coarse score a job needs to make the leaderboard, only written while holding its lock
+        let entry_score = AtomicU64::new(0);
+        // index of the job whose result reached the criteria's done score, once
+        // one has; later jobs are skipped
+        let done = self.criteria_predicate.done();
+        let finished_job = AtomicUsize::new(usize::MAX);
+        // Under a seed, job results reach the board in job order, and only jobs
+        // after the one that reached the done score are skipped, so the jobs
+        // searched and the board kept don't depend on thread scheduling. Results
+        // of jobs that end ahead of an earlier one wait here until it has ended.
+        let in_order = self.number_generator.is_reproducible();
+        let waiting: Mutex<(Coverage, BTreeMap<usize, SearchResult>)> = Mutex::default();
+        let completed_jobs = &self.progress.completed_jobs;
+        let track_coverage = self.number_generator.job_range(0..0).is_some();
+        let coverage = Mutex::new(Coverage::default());
+        // leaderboard file snapshots are numbered under the board's lock and written outside it
+        let board_version = AtomicU64::new(0);
+        let written_version = Mutex::new(0);
 
         // logging
         let num_completed_jobs_log_width = format!("{}", self.num_jobs).len();
         let num_threads_log_width = format!("{}", self.thread_pool.current_num_threads()).len();
-        let num_searches_log_width = format!("{}", self.num_jobs * self.attempts_per_job).len();
+        let num_searches_log_width =
+            format!("{}", self.num_jobs * (self.attempts_per_job + 1)).len();
 
         self.thread_pool.install(|| {
             (0..self.num_jobs)
                 .into_par_iter()
                 .enumerate()
                 .for_each_with(
-                    best_address.clone(),
-                    |best: &mut Arc<Mutex<String>>, (_job_num, _worker_id)| {
+                    leaderboard.clone(),
+                    |board: &mut Arc<Mutex<Leaderboard>>, (job_num, _worker_id)| {
+                        self.progress.started_jobs.fetch_add(1, Ordering::Relaxed);
+                        let finished = match finished_job.load(Ordering::Acquire) {
+                            usize::MAX => false,
+                            done_job => !in_order || job_num > done_job,
+                        };
+                        if finished || self.progress.is_cancelled() {
+                            return;
+                        }
 
-                        // Criteria gets moved here, so we need to clone it
-                        // but it's a box so we need to clone the box
-                        let mut searcher: Searcher =
+                        // Each job gets its own copies; for concrete types this is
+                        // a cheap struct copy rather than a boxed allocation
+                        let mut number_generator = self.number_generator.clone();
+                        number_generator.start_job(job_num);
+                        let mut searcher =
                             Searcher::new(
-                                self.number_generator.clone_box(),
-                                self.address_generator.clone_box(),
-                                self.criteria_predicate.clone_box(),
+                                number_generator,
+                                self.address_generator.clone(),
+                                self.criteria_predicate.clone(),
                                 self.attempts_per_job);
-                        let found: SearchResult = searcher.run();
-                        let found_address: &str = found.address.as_str();
+                        if track_coverage {
+                            // the whole job range goes into the coverage log
+                            searcher = searcher.run_to_end();
+                        }
+                        let JobResult { best: found, saves, attempts } = searcher.run(&self.saver);
                         let num_completed_jobs = completed_jobs.fetch_add(1, Ordering::SeqCst) + 1;
-                        let num_completed_searches: usize = num_completed_jobs * self.attempts_per_job;
-
-                        let mut best_address_guard: MutexGuard<String> = best.lock().unwrap();
-
-                        let better: bool = self.criteria_predicate.better(found_address, &*best_address_guard);
-                        if better {
-                            *best_address_guard = String::from(found_address);
+                        if let Some(thread_attempts) = current_thread_index().and_then(|i| self.progress.thread_attempts.get(i)) {
+                            thread_attempts.fetch_add(attempts, Ordering::Relaxed);
                         }
+                        let num_completed_searches: usize = self.progress.attempts.fetch_add(attempts, Ordering::SeqCst) + attempts;
 
-                        let save = found_address.starts_with("0x00000000");
-
-                        let s: &str = if better { "best" } else if save { "save" } else { "----" };
-                        let address: &str = if better || save { found_address } else { best_address_guard.as_str() };
-
-                        if better || save || (num_completed_jobs % 1000) == 0{
+                        let periodic = num_completed_jobs.is_multiple_of(1000);
+                        let log = |status: &str, address: &str, score: &Score, tag: Option<&str>| {
+                            let address: String = to_checksum_address(address);
+                            let tag_column: String = tag.map(|tag| format!("     {}", tag)).unwrap_or_default();
                             let thread_index = current_thread_index().unwrap_or(0);
-                            info!(
-                                "Thread #{:twidth$}     Job #{:jwidth$}     Try #{:swidth$}     {}     {}",
+                            let message = format!(
+                                "Thread #{:twidth$}     Job #{:jwidth$}     Try #{:swidth$}     {}     {}     score {}{}",
                                 thread_index.to_formatted_string(&Locale::en),
                                 num_completed_jobs.to_formatted_string(&Locale::en),
                                 num_completed_searches.to_formatted_string(&Locale::en),
-                                s,
+                                status,
                                 address,
+                                score,
+                                tag_column,
                                 twidth = num_threads_log_width,
                                 jwidth = num_completed_jobs_log_width,
                                 swidth = num_searches_log_width
                             );
+                            // `matched` is only a field on the lines that matched something
+                            match tag {
+                                Some(tag) => info!(
+                                    thread = thread_index,
+                                    job = num_completed_jobs,
+                                    attempts = num_completed_searches,
+                                    status = status,
+                                    address = address.as_str(),
+                                    score = score.headline(),
+                                    matched = tag;
+                                    "{}", message
+                                ),
+                                None => info!(
+                                    thread = thread_index,
+                                    job = num_completed_jobs,
+                                    attempts = num_completed_searches,
+                                    status = status,
+                                    address = address.as_str(),
+                                    score = score.headline();
+                                    "{}", message
+                                ),
+                            }
+                        };
+
+                        // Fast path: a job whose coarse score is below the leaderboard's
+                        // entry score can't make it, so the lock is only taken to update, save or log.
+                        let candidate = found.score.coarse() >= entry_score.load(Ordering::Acquire);
+                        if candidate {
+                            self.check(&found.address, &found.seed);
+                        }
+                        for (_, saved) in &saves {
+                            self.check(&saved.address, &saved.seed);
                         }
+                        // held until this job's results are on the board, keeping them in job order
+                        let mut waiting_guard = in_order.then(|| waiting.lock().unwrap());
+                        let offers: Vec<(usize, SearchResult)> = match waiting_guard.as_deref_mut() {
+                            Some((ended, results)) => {
+                                if candidate {
+                                    results.insert(job_num, found);
+                                }
+                                ended.complete(job_num);
+                                let later = results.split_off(&ended.watermark);
+                                std::mem::replace(results, later).into_iter().collect()
+                            }
+                            None if candidate => vec![(job_num, found)],
+                            None => Vec::new(),
+                        };
+                        // written to the leaderboard file once the board's lock is released
+                        let mut snapshot = None;
+                        if !offers.is_empty() || !saves.is_empty() || periodic {
+                            let mut board_guard: MutexGuard<Leaderboard> = board.lock().unwrap();
+
+                            let mut changed = false;
+                            let mut better = false;
+                            for (job, result) in offers {
+                                if in_order && job > finished_job.load(Ordering::Acquire) {
+                                    break;
+                                }
+                                let (address, score) = (result.address.clone(), result.score.clone());
+                                let rank = board_guard.offer(result);
+                                changed |= rank.is_some();
+                                if rank != Some(0) {
+                                    continue;
+                                }
+                                better = true;
+                                *self.progress.best.lock().unwrap() = Some((address.clone(), score.clone()));
+                                let reached = done.as_ref().is_some_and(|done| score.reaches(done));
+                                if reached {
+                                    finished_job.fetch_min(job, Ordering::AcqRel);
+                                }
+                                log(if reached { "done" } else { "best" }, &address, &score, None);
+                            }
+                            if changed {
+                                if board_guard.is_full() {
+                                    let worst = board_guard.worst().map(|e| e.score.coarse()).unwrap_or_default();
+                                    entry_score.store(worst, Ordering::Release);
+                                }
+                                if self.persist_leaderboard {
+                                    let version = board_version.fetch_add(1, Ordering::Relaxed) + 1;
+                                    snapshot = Some((version, board_guard.snapshot(&self.address_generator, self.sealed_to.as_ref())));
+                                }
+                            }
+                            if periodic && !better {
+                                if let Some(best) = board_guard.best() {
+                                    log("----", &best.address, &best.score, None);
+                                }
+                            }
+
+                            for (tag, saved) in &saves {
+                                log("save", &saved.address, &saved.score, Some(tag));
+                                let secret = self.address_generator.secret(&saved.seed);
+                                let record = SaveRecord {
+                                    address: &saved.address,
+                                    secret: &secret,
+                                    generator: self.address_generator.kind(),
+                                    derivation: self.address_generator.derivation(&saved.seed),
+                                    score: &saved.score,
+                                    tag,
+                                    attempts: num_completed_searches,
+                                };
+                                match self.saver.save(&record) {
+                                    Ok(()) => _ = self.progress.saves.fetch_add(1, Ordering::Relaxed),
+                                    Err(e) => warn!("Failed to save {}: {}", saved.address, e),
+                                }
+                            }
+                        }
+
+                        drop(waiting_guard);
 
-                        if save {
-                            let mnemonic: Mnemonic = Mnemonic::from_entropy(found.seed, Language::English);
-                            mnemonic_log!("{} {}", found_address, mnemonic.phrase());
+                        if let Some((version, snapshot)) = snapshot {
+                            // a thread that took a newer snapshot may have got here first
+                            let mut written = written_version.lock().unwrap();
+                            if version > *written {
+                                *written = version;
+                                if let Err(e) = persist(Path::new(LEADERBOARD_FILE), &snapshot) {
+                                    warn!("Failed to persist leaderboard: {}", e);
+                                }
+                            }
+                        }
+
+                        if track_coverage {
+                            let mut coverage_guard = coverage.lock().unwrap();
+                            coverage_guard.complete(job_num);
+                            if let Some(jobs) = coverage_guard.take(1000) {
+                                self.log_coverage(jobs);
+                            }
                         }
                     },
                 );
         });
+        self.progress.ended.get_or_init(Instant::now);
+
+        if let Some(jobs) = coverage.lock().unwrap().take(1) {
+            self.log_coverage(jobs);
+        }
+        if let Err(e) = self.saver.finish(self.progress.attempts()) {
+            warn!("Failed to record the end of the run: {}", e);
+        }
+
+        Arc::try_unwrap(leaderboard)
+            .ok()
+            .expect("All jobs have finished with the leaderboard")
+            .into_inner()
+            .unwrap()
+    }
+
+    /// Rebuild `found` from its seed along the generator's reference path and
+    /// abort the whole process if the addresses disagree: a result that can't be
+    /// reproduced must never be saved, persisted or returned.
+    fn check(&self, address: &str, seed: &Entropy) {
+        let reference = self.address_generator.reference(seed);
+        if reference.as_deref().ok() == Some(address) {
+            return;
+        }
+        let reference = reference
+            .map(|address| to_checksum_address(&address))
+            .unwrap_or_else(|e| format!("an error ({})", e));
+        error!(
+            "VERIFICATION FAILED: search found {} but its seed rebuilds to {}; aborting",
+            to_checksum_address(address),
+            reference
+        );
+        log::logger().flush();
+        std::process::abort();
+    }
+
+    fn log_coverage(&self, jobs: Range<usize>) {
+        if let Some((start, end)) = self.number_generator.job_range(jobs.clone()) {
+            coverage_log!(
+                "{} {} jobs {}..{}",
+                hex::encode(start),
+                hex::encode(end),
+                jobs.start,
+                jobs.end
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::criteria::{LeadingZeroBytesCriteria, PrefixCriteria};
+    use crate::crypto::Create2AddressGenerator;
+    use crate::randnum::SeededNumberGenerator;
+    use crate::save::SaveRule;
+    use rayon::ThreadPoolBuilder;
+
+    const CREATE2: Create2AddressGenerator = Create2AddressGenerator {
+        deployer: [0x11; 20],
+        init_code_hash: [0x22; 32],
+    };
+
+    /// Top five of a seeded search for leading zero bytes over 64 jobs of 21 attempts.
+    const EXPECTED_BOARD: [&str; 5] = [
+        "0x00136d73f380393d4f8f774020a975f65f6eeabc",
+        "0x00498c37df514e5b4738257a1a9a7f6ead63e065",
+        "0x004f539cfb8d1306e65596012d4be0c9a28bf93a",
+        "0x0050bc74fc254b44d18a857a4fa9222af75fc939",
+        "0x006a5b56c787e2ad5a97ae499e67e8c1785b242c",
+    ];
+
+    /// The board of a seeded CREATE2 search on `num_threads` threads.
+    fn seeded_board<C>(num_threads: usize, criteria: C) -> Vec<String>
+    where
+        C: CriteriaPredicate + Clone + Send + Sync,
+    {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let leaderboard = ThreadPoolSearcher::new(
+            Arc::new(thread_pool),
+            64,
+            20,
+            5,
+            SeededNumberGenerator::new(1),
+            CREATE2,
+            criteria,
+        )
+        .with_saver(Saver {
+            rule: SaveRule::Never,
+            words: None,
+            sinks: Vec::new(),
+        })
+        .without_leaderboard_file()
+        .run();
+        leaderboard
+            .entries()
+            .iter()
+            .map(|entry| entry.address.clone())
+            .collect()
+    }
+
+    #[test]
+    fn seeded_search_finds_the_same_board_on_any_number_of_threads() {
+        let expected = seeded_board(1, LeadingZeroBytesCriteria);
+        assert_eq!(expected, EXPECTED_BOARD);
+        for num_threads in [2, 4, 8] {
+            assert_eq!(
+                seeded_board(num_threads, LeadingZeroBytesCriteria),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn seeded_search_that_reaches_its_goal_searches_the_same_jobs() {
+        let prefix = PrefixCriteria::new("00", false).unwrap();
+        let expected = seeded_board(1, prefix.clone());
+        assert!(expected[0].starts_with("0x00"));
+        for num_threads in [2, 4, 8] {
+            assert_eq!(seeded_board(num_threads, prefix.clone()), expected);
+        }
+    }
+
+    #[test]
+    fn coverage_advances_over_contiguous_jobs() {
+        let mut coverage = Coverage::default();
+        coverage.complete(1);
+        coverage.complete(2);
+        assert_eq!(coverage.watermark, 0);
+        assert_eq!(coverage.take(1), None);
+
+        coverage.complete(0);
+        assert_eq!(coverage.watermark, 3);
+        assert!(coverage.pending.is_empty());
+
+        // nothing is taken until the new range is long enough
+        assert_eq!(coverage.take(4), None);
+        assert_eq!(coverage.take(3), Some(0..3));
+        assert_eq!(coverage.take(1), None);
+
+        coverage.complete(4);
+        coverage.complete(3);
+        assert_eq!(coverage.take(1), Some(3..5));
+    }
+
+    /// Generates an address that its own `reference` disagrees with.
+    #[derive(Copy, Clone)]
+    struct Miscomputed;
+
+    impl AddressGenerator for Miscomputed {
+        fn generate(&self, _entropy: &Entropy) -> Result<String, bip32::Error> {
+            Ok(format!("0x{}", "00".repeat(20)))
+        }
+
+        fn secret(&self, entropy: &Entropy) -> Zeroizing<String> {
+            CREATE2.secret(entropy)
+        }
+
+        fn reference(&self, entropy: &Entropy) -> Result<String, bip32::Error> {
+            CREATE2.reference(entropy)
+        }
+
+        fn kind(&self) -> &'static str {
+            "miscomputed"
+        }
+
+        fn derivation(&self, entropy: &Entropy) -> (&'static str, Zeroizing<String>) {
+            CREATE2.derivation(entropy)
+        }
+
+        fn clone_box(&self) -> Box<dyn AddressGenerator + Send + Sync> {
+            Box::new(*self)
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn search_aborts_when_the_reference_disagrees() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{Command, Stdio};
+
+        // an abort takes the whole process down, so the search runs in a child
+        // process of this same test
+        if std::env::var_os("VANITYGEN_ABORT_TEST").is_some() {
+            let thread_pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+            ThreadPoolSearcher::new(
+                Arc::new(thread_pool),
+                1,
+                1,
+                1,
+                SeededNumberGenerator::new(1),
+                Miscomputed,
+                LeadingZeroBytesCriteria,
+            )
+            .with_saver(Saver {
+                rule: SaveRule::Never,
+                words: None,
+                sinks: Vec::new(),
+            })
+            .without_leaderboard_file()
+            .run();
+            return;
+        }
 
-        let best_address_guard: MutexGuard<String> = best_address.lock().unwrap();
-        best_address_guard.clone()
+        let status = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "search::tests::search_aborts_when_the_reference_disagrees",
+            ])
+            .env("VANITYGEN_ABORT_TEST", "1")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGABRT), "{}", status);
     }
 }
@@ -0,0 +1,121 @@
+use std::{
+    alloc::{self, Layout},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::warn;
+use zeroize::Zeroize;
+
+static WARNED_MLOCK: AtomicBool = AtomicBool::new(false);
+
+/// A secret on the heap that is kept out of swap where possible and wiped on drop.
+///
+/// Every value gets its own page-aligned allocation: `mlock` is not reference
+/// counted, so two values sharing a page would unlock each other on drop.
+pub struct Locked<T: Zeroize> {
+    ptr: NonNull<T>,
+    layout: Layout,
+    locked: bool,
+}
+
+impl<T: Zeroize> Locked<T> {
+    pub fn new(value: T) -> Self {
+        let page = page_size();
+        let size = std::mem::size_of::<T>().max(1).div_ceil(page) * page;
+        let layout = Layout::from_size_align(size, page.max(std::mem::align_of::<T>()))
+            .expect("Invalid layout for locked secret");
+
+        // SAFETY: layout has a non-zero size, and the pointer is checked for null
+        // before being written with a properly aligned `T`.
+        let ptr = unsafe {
+            let raw = alloc::alloc(layout) as *mut T;
+            let ptr = NonNull::new(raw).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+            ptr::write(ptr.as_ptr(), value);
+            ptr
+        };
+
+        let locked = lock(ptr.as_ptr() as *const u8, size);
+        if !locked && !WARNED_MLOCK.swap(true, Ordering::Relaxed) {
+            warn!("Could not mlock secrets; they may be swapped to disk (check RLIMIT_MEMLOCK)");
+        }
+
+        Self {
+            ptr,
+            layout,
+            locked,
+        }
+    }
+}
+
+impl<T: Zeroize> Deref for Locked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: ptr was initialised in `new` and is only freed in `drop`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: Zeroize> DerefMut for Locked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: ptr was initialised in `new` and is only freed in `drop`.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Zeroize> Drop for Locked<T> {
+    fn drop(&mut self) {
+        // SAFETY: ptr holds an initialised `T` allocated with `self.layout`;
+        // nothing touches it after this point.
+        unsafe {
+            self.ptr.as_mut().zeroize();
+            ptr::drop_in_place(self.ptr.as_ptr());
+            if self.locked {
+                unlock(self.ptr.as_ptr() as *const u8, self.layout.size());
+            }
+            alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout);
+        }
+    }
+}
+
+// SAFETY: Locked<T> uniquely owns its T, exactly like Box<T>.
+unsafe impl<T: Zeroize + Send> Send for Locked<T> {}
+unsafe impl<T: Zeroize + Sync> Sync for Locked<T> {}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions.
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+#[cfg(not(unix))]
+fn page_size() -> usize {
+    4096
+}
+
+#[cfg(unix)]
+fn lock(addr: *const u8, len: usize) -> bool {
+    // SAFETY: addr..addr+len is a live allocation owned by the caller.
+    unsafe { libc::mlock(addr as *const libc::c_void, len) == 0 }
+}
+
+#[cfg(not(unix))]
+fn lock(_addr: *const u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn unlock(addr: *const u8, len: usize) {
+    // SAFETY: addr..addr+len is a live allocation previously passed to mlock.
+    unsafe {
+        libc::munlock(addr as *const libc::c_void, len);
+    }
+}
+
+#[cfg(not(unix))]
+fn unlock(_addr: *const u8, _len: usize) {}
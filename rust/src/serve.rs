@@ -0,0 +1,422 @@
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use bip32::Language;
+use k256::PublicKey;
+use log::{info, warn};
+use rayon::ThreadPool;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    criteria::parse_submitted_criteria,
+    crypto::{
+        to_checksum_address, AddressGenerator, Create2AddressGenerator, MnemonicAddressGenerator,
+    },
+    metrics, parse_hex_array,
+    randnum::RandNumberGenerator,
+    save::{SaveRule, Saver},
+    seal,
+    search::{SearchProgress, ThreadPoolSearcher},
+};
+
+/// Largest `POST /jobs` body read, in bytes.
+const MAX_BODY: usize = 64 * 1024;
+
+/// A search submitted with `POST /jobs`, e.g.
+///
+/// ```json
+/// {"criteria": "prefix:dead", "jobs": 10000, "public_key": "03..."}
+/// ```
+///
+/// `criteria` takes the `--rank` syntax and defaults to `less-than`, though
+/// `words:` criteria may only name a wordlist in the server's `--wordlist-dir`;
+/// `jobs` defaults to 1,000,000, and `each` and `top` default as in `search`.
+/// CREATE2 salts are mined instead of phrases when `create2_deployer` and
+/// `init_code_hash` are given.
+struct JobSpec {
+    criteria: String,
+    case_sensitive: bool,
+    create2: Option<Create2AddressGenerator>,
+    num_jobs: usize,
+    attempts_per_job: usize,
+    top: usize,
+    /// Secrets in the job's results are sealed to this key.
+    public_key: PublicKey,
+}
+
+impl JobSpec {
+    fn parse(body: &str) -> Result<Self, String> {
+        let spec: Value =
+            serde_json::from_str(body).map_err(|e| format!("body is not JSON: {}", e))?;
+        let string = |key: &str| -> Result<Option<&str>, String> {
+            match spec.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s)),
+                Some(_) => Err(format!("'{}' must be a string", key)),
+            }
+        };
+        let count = |key: &str, default: usize| -> Result<usize, String> {
+            match spec.get(key) {
+                None | Some(Value::Null) => Ok(default),
+                Some(value) => value
+                    .as_u64()
+                    .filter(|n| *n > 0)
+                    .map(|n| n as usize)
+                    .ok_or_else(|| format!("'{}' must be a positive integer", key)),
+            }
+        };
+
+        let public_key = string("public_key")?
+            .ok_or_else(|| "'public_key' is required, to seal the results' secrets to".to_string())
+            .and_then(seal::parse_public_key)?;
+        let create2 = match (string("create2_deployer")?, string("init_code_hash")?) {
+            (Some(deployer), Some(init_code_hash)) => Some(Create2AddressGenerator {
+                deployer: parse_hex_array(deployer)?,
+                init_code_hash: parse_hex_array(init_code_hash)?,
+            }),
+            (None, None) => None,
+            _ => return Err("'create2_deployer' and 'init_code_hash' go together".into()),
+        };
+        Ok(Self {
+            criteria: string("criteria")?.unwrap_or("less-than").to_string(),
+            case_sensitive: spec
+                .get("case_sensitive")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            create2,
+            num_jobs: count("jobs", 1_000_000)?,
+            attempts_per_job: count("each", 1_000)?,
+            top: count("top", 20)?,
+            public_key,
+        })
+    }
+}
+
+struct Job {
+    spec: JobSpec,
+    progress: Arc<SearchProgress>,
+    started: Instant,
+    started_at: String,
+    /// The final leaderboard with its secrets sealed, once the search has ended.
+    results: Arc<Mutex<Option<Value>>>,
+}
+
+impl Job {
+    fn state(&self) -> &'static str {
+        match (
+            self.results.lock().unwrap().is_some(),
+            self.progress.is_cancelled(),
+        ) {
+            (false, false) => "running",
+            (false, true) => "cancelling",
+            (true, false) => "done",
+            (true, true) => "cancelled",
+        }
+    }
+
+    fn stats(&self, id: u64) -> Value {
+        let attempts = self.progress.attempts();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        json!({
+            "id": id,
+            "state": self.state(),
+            "criteria": self.spec.criteria,
+            "generator": if self.spec.create2.is_some() { "create2" } else { "mnemonic" },
+            "attempts": attempts,
+            // each job also scores its initial candidate
+            "max_attempts": self.spec.num_jobs * (self.spec.attempts_per_job + 1),
+            "attempts_per_second": if elapsed > 0.0 { attempts as f64 / elapsed } else { 0.0 },
+            "started": self.started_at,
+            "best": self.progress.best().map(|(address, score)| json!({
+                "address": to_checksum_address(&address),
+                "score": score.0,
+            })),
+        })
+    }
+}
+
+/// How much of the machine submitted searches may hold.
+pub struct Limits {
+    /// Searches kept at once, running or with results not yet fetched.
+    pub max_jobs: usize,
+    /// Largest `jobs` × `each` a search may ask for.
+    pub max_attempts: usize,
+    /// Directory whose wordlists `words:` criteria may name; without one
+    /// they're refused, so that callers can't make the server read its files.
+    pub wordlist_dir: Option<PathBuf>,
+}
+
+/// Searches submitted over HTTP, all sharing one thread pool.
+struct Jobs {
+    thread_pool: Arc<ThreadPool>,
+    limits: Limits,
+    started: Instant,
+    /// Ids are never reused, even once a job is forgotten.
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+}
+
+impl Jobs {
+    /// Start a search, returning its id, or an HTTP status and why it was refused.
+    fn submit(&self, spec: JobSpec) -> Result<u64, (u16, String)> {
+        let attempts = spec.num_jobs.checked_mul(spec.attempts_per_job);
+        if attempts.is_none_or(|attempts| attempts > self.limits.max_attempts) {
+            return Err((
+                400,
+                format!(
+                    "'jobs' x 'each' must be at most {}",
+                    self.limits.max_attempts
+                ),
+            ));
+        }
+        let criteria = parse_submitted_criteria(
+            &spec.criteria,
+            spec.case_sensitive,
+            self.limits.wordlist_dir.as_deref(),
+        )
+        .map_err(|e| (400, e))?;
+        let address_generator: Box<dyn AddressGenerator + Send + Sync> = match spec.create2 {
+            Some(create2) => Box::new(create2),
+            None => Box::new(MnemonicAddressGenerator {
+                language: Language::English,
+            }),
+        };
+        let progress = Arc::new(SearchProgress::new(self.thread_pool.current_num_threads()));
+        let results = Arc::new(Mutex::new(None));
+        let searcher = ThreadPoolSearcher::new(
+            self.thread_pool.clone(),
+            spec.num_jobs,
+            spec.attempts_per_job,
+            spec.top,
+            RandNumberGenerator {},
+            address_generator.clone(),
+            criteria,
+        )
+        // results only leave the process sealed, through the API
+        .with_saver(Saver {
+            rule: SaveRule::Never,
+            words: None,
+            sinks: Vec::new(),
+        })
+        .with_progress(progress.clone())
+        .without_leaderboard_file();
+
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.len() >= self.limits.max_jobs {
+            return Err((
+                429,
+                format!(
+                    "{} jobs are already held; fetch the results of finished jobs, or delete them",
+                    jobs.len()
+                ),
+            ));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        info!("Job {}: searching '{}'", id, spec.criteria);
+
+        let public_key = spec.public_key;
+        let job_results = results.clone();
+        thread::Builder::new()
+            .name(format!("job-{}", id))
+            .spawn(move || {
+                let leaderboard = searcher.run();
+                let sealed: Vec<Value> = leaderboard
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, entry)| {
+                        let secret = address_generator.secret(&entry.seed);
+                        json!({
+                            "rank": rank + 1,
+                            "address": entry.address,
+                            "checksum_address": to_checksum_address(&entry.address),
+                            "score": entry.score.0,
+                            "sealed_secret": seal::seal(&public_key, secret.as_bytes()),
+                        })
+                    })
+                    .collect();
+                info!("Job {}: finished with {} results", id, sealed.len());
+                *job_results.lock().unwrap() = Some(Value::Array(sealed));
+            })
+            .map_err(|e| (500, format!("failed to start the job: {}", e)))?;
+
+        jobs.insert(
+            id,
+            Job {
+                spec,
+                progress,
+                started: Instant::now(),
+                started_at: chrono::Local::now().to_rfc3339(),
+                results,
+            },
+        );
+        Ok(id)
+    }
+}
+
+fn respond(request: Request, status: u16, body: Value) {
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("a static header is valid");
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        warn!("Failed to send a response: {}", e);
+    }
+}
+
+fn error(request: Request, status: u16, message: impl Into<String>) {
+    respond(request, status, json!({ "error": message.into() }));
+}
+
+fn handle(jobs: &Jobs, mut request: Request) {
+    let method = request.method().clone();
+    let url = request.url().trim_end_matches('/').to_string();
+    let path: Vec<&str> = url.split('/').skip(1).collect();
+    let id = path.get(1).map(|id| id.parse::<u64>());
+
+    match (&method, path.as_slice()) {
+        (Method::Post, ["jobs"]) => {
+            let mut body = String::new();
+            let read = request
+                .as_reader()
+                .take(MAX_BODY as u64 + 1)
+                .read_to_string(&mut body);
+            if let Err(e) = read {
+                return error(request, 400, format!("failed to read the body: {}", e));
+            }
+            if body.len() > MAX_BODY {
+                return error(request, 413, format!("the body is over {} bytes", MAX_BODY));
+            }
+            match JobSpec::parse(&body)
+                .map_err(|e| (400, e))
+                .and_then(|spec| jobs.submit(spec))
+            {
+                Ok(id) => respond(request, 201, json!({ "id": id })),
+                Err((status, e)) => error(request, status, e),
+            }
+        }
+        (Method::Get, ["metrics"]) => {
+            let jobs_guard = jobs.jobs.lock().unwrap();
+            let sources: Vec<metrics::Source> = jobs_guard
+                .iter()
+                .map(|(id, job)| (Some(*id), &*job.progress))
+                .collect();
+            let mut body = metrics::render(&sources, jobs.started);
+            let running = jobs_guard
+                .values()
+                .filter(|job| job.results.lock().unwrap().is_none())
+                .count();
+            drop(jobs_guard);
+            metrics::metric(
+                &mut body,
+                "running_searches",
+                "gauge",
+                "Submitted searches sharing the thread pool, including ones being cancelled.",
+                [(String::new(), running as f64)],
+            );
+            metrics::respond(request, Some(body));
+        }
+        (Method::Get, ["jobs"]) => {
+            let stats: Vec<Value> = jobs
+                .jobs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, job)| job.stats(*id))
+                .collect();
+            respond(request, 200, Value::Array(stats));
+        }
+        (_, ["jobs", _, ..]) => {
+            let Some(Ok(id)) = id else {
+                return error(request, 404, "no such job");
+            };
+            let mut guard = jobs.jobs.lock().unwrap();
+            let Some(job) = guard.get(&id) else {
+                drop(guard);
+                return error(request, 404, format!("no job {}", id));
+            };
+            let finished = job.results.lock().unwrap().is_some();
+            // finished jobs are forgotten once their results are fetched or deleted
+            let (status, body, forget) = match (&method, &path[2..]) {
+                (Method::Get, []) => (200, job.stats(id), false),
+                (Method::Delete, []) if finished => (200, job.stats(id), true),
+                (Method::Delete, []) => {
+                    job.progress.cancel();
+                    info!("Job {}: cancelled", id);
+                    (202, job.stats(id), false)
+                }
+                (Method::Get, ["results"]) => {
+                    // before locking the results, which state() also locks
+                    let state = job.state();
+                    match &*job.results.lock().unwrap() {
+                        Some(results) => (
+                            200,
+                            json!({ "id": id, "state": state, "results": results }),
+                            true,
+                        ),
+                        None => (
+                            409,
+                            json!({ "error": "the job is still running; cancel it to end it early" }),
+                            false,
+                        ),
+                    }
+                }
+                _ => (404, json!({ "error": "unknown endpoint" }), false),
+            };
+            if forget {
+                guard.remove(&id);
+                info!("Job {}: forgotten", id);
+            }
+            drop(guard);
+            respond(request, status, body);
+        }
+        _ => error(request, 404, "unknown endpoint"),
+    }
+}
+
+/// Serve the job API on `listen` until the process is stopped:
+///
+/// * `POST /jobs` - submit a search (see `JobSpec`), returns its id
+/// * `GET /jobs`, `GET /jobs/<id>` - live stats: state, attempts, rate and best result
+/// * `DELETE /jobs/<id>` - cancel a search, or forget a finished one
+/// * `GET /jobs/<id>/results` - the final leaderboard, secrets sealed to the
+///   job's key; the job is forgotten once its results are fetched
+/// * `GET /metrics` - every job's counters for Prometheus, labelled by job id, and
+///   how many searches share the thread pool
+///
+/// Searches are refused once `limits.max_jobs` are held, so results that are
+/// never fetched can't pile up in memory.
+///
+/// There is no authentication: anyone who can reach `listen` can use the
+/// machine's threads, though only the submitter's key opens the results.
+pub fn run(listen: &str, thread_pool: ThreadPool, limits: Limits) -> Result<(), String> {
+    let server =
+        Server::http(listen).map_err(|e| format!("failed to listen on {}: {}", listen, e))?;
+    info!(
+        "Serving the job API on http://{} with {} threads shared by all jobs",
+        listen,
+        thread_pool.current_num_threads()
+    );
+    let jobs = Jobs {
+        thread_pool: Arc::new(thread_pool),
+        limits,
+        started: Instant::now(),
+        next_id: AtomicU64::new(1),
+        jobs: Mutex::new(BTreeMap::new()),
+    };
+    for request in server.incoming_requests() {
+        handle(&jobs, request);
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use bip32::{DerivationPath, Language};
+use rayon::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{
+        parse_phrase, to_checksum_address, AddressGenerator, Create2AddressGenerator,
+        MnemonicAddressGenerator,
+    },
+    randnum::Entropy,
+};
+
+/// How to re-derive the addresses in a results file.
+pub struct Verifier {
+    pub language: Language,
+    /// Path for lines that don't record one.
+    pub path: DerivationPath,
+    /// For lines holding a CREATE2 salt instead of a phrase.
+    pub create2: Option<Create2AddressGenerator>,
+}
+
+/// One result line: `[<rank>] <address> <phrase or salt> [<path>]`, covering
+/// `mnemonics.log`, `file:` save sinks and the leaderboard file.
+struct Entry {
+    line: usize,
+    address: String,
+    secret: Zeroizing<String>,
+    path: Option<DerivationPath>,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub verified: usize,
+    pub mismatched: usize,
+    pub duplicates: usize,
+    pub malformed: usize,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.mismatched == 0 && self.malformed == 0
+    }
+}
+
+fn parse_line(line: usize, text: &str) -> Result<Option<Entry>, String> {
+    let mut tokens: Vec<&str> = text.split_whitespace().collect();
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    // leaderboard lines lead with their rank
+    if tokens[0].chars().all(|c| c.is_ascii_digit()) {
+        tokens.remove(0);
+    }
+    let Some((address, rest)) = tokens.split_first() else {
+        return Err("no address".into());
+    };
+    let hex = address.strip_prefix("0x").unwrap_or_default();
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not an address", address));
+    }
+    let (path, secret) = match rest.split_last() {
+        Some((last, secret)) if last.starts_with("m/") => {
+            let path = last
+                .parse()
+                .map_err(|_| format!("'{}' is not a derivation path", last))?;
+            (Some(path), secret)
+        }
+        _ => (None, rest),
+    };
+    if secret.is_empty() {
+        return Err("no phrase or salt".into());
+    }
+    Ok(Some(Entry {
+        line,
+        address: address.to_string(),
+        secret: Zeroizing::new(secret.join(" ")),
+        path,
+    }))
+}
+
+impl Verifier {
+    /// The address the entry's secret derives to.
+    fn derive(&self, entry: &Entry) -> Result<String, String> {
+        let secret = entry.secret.as_str();
+        if secret.starts_with("0x") && !secret.contains(' ') {
+            let create2 = self
+                .create2
+                .as_ref()
+                .ok_or("a CREATE2 salt needs --create2-deployer and --init-code-hash")?;
+            let mut salt: Zeroizing<Entropy> = Zeroizing::new([0; 32]);
+            hex::decode_to_slice(&secret[2..], salt.as_mut())
+                .map_err(|_| "the salt is not 64 hex characters".to_string())?;
+            return create2.generate(&salt).map_err(|e| e.to_string());
+        }
+        let mnemonic = parse_phrase(secret, self.language)?;
+        MnemonicAddressGenerator {
+            language: self.language,
+        }
+        .address_at(&mnemonic, entry.path.as_ref().unwrap_or(&self.path))
+        .map_err(|e| e.to_string())
+    }
+
+    /// Re-derive every line of `input`, printing each problem by line number
+    /// and address. Secrets are never printed.
+    pub fn verify(&self, input: impl BufRead) -> io::Result<Report> {
+        let mut report = Report::default();
+        let mut entries = Vec::new();
+        for (i, text) in input.lines().enumerate() {
+            let text = Zeroizing::new(text?);
+            match parse_line(i + 1, &text) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => {
+                    println!("MALFORMED  line {}: {}", i + 1, e);
+                    report.malformed += 1;
+                }
+            }
+        }
+
+        let mut first_seen: HashMap<String, usize> = HashMap::new();
+        for entry in &entries {
+            let key = entry.address.to_ascii_lowercase();
+            if let Some(first) = first_seen.get(&key) {
+                println!(
+                    "DUPLICATE  line {}: {} already on line {}",
+                    entry.line, entry.address, first
+                );
+                report.duplicates += 1;
+            } else {
+                first_seen.insert(key, entry.line);
+            }
+        }
+
+        let derived: Vec<Result<String, String>> =
+            entries.par_iter().map(|entry| self.derive(entry)).collect();
+        for (entry, derived) in entries.iter().zip(derived) {
+            match derived {
+                Err(e) => {
+                    println!("MALFORMED  line {}: {}", entry.line, e);
+                    report.malformed += 1;
+                }
+                Ok(derived) if !derived.eq_ignore_ascii_case(&entry.address) => {
+                    println!(
+                        "MISMATCH   line {}: logged {}, derives {}",
+                        entry.line,
+                        entry.address,
+                        to_checksum_address(&derived)
+                    );
+                    report.mismatched += 1;
+                }
+                // a mixed-case address has to carry a valid EIP-55 checksum too
+                Ok(derived)
+                    if entry.address[2..].chars().any(|c| c.is_ascii_uppercase())
+                        && to_checksum_address(&derived) != entry.address =>
+                {
+                    println!(
+                        "MISMATCH   line {}: logged {} has a bad checksum, expected {}",
+                        entry.line,
+                        entry.address,
+                        to_checksum_address(&derived)
+                    );
+                    report.mismatched += 1;
+                }
+                Ok(_) => report.verified += 1,
+            }
+        }
+        Ok(report)
+    }
+
+    /// Verify the results file at `path`, or stdin for `-`.
+    pub fn verify_file(&self, path: &Path) -> io::Result<Report> {
+        if path == Path::new("-") {
+            self.verify(io::stdin().lock())
+        } else {
+            self.verify(BufReader::new(File::open(path)?))
+        }
+    }
+}